@@ -0,0 +1,30 @@
+// Any program that type-checks must run without panicking. The step limit keeps
+// infinite loops from stalling the fuzzer, and the output limit keeps one that writes a
+// long string from using up its memory; `scan` is skipped since it reads stdin.
+#![no_main]
+
+use aoi::interpreter::Interpreter;
+use aoi::parser::Parser;
+use aoi::token::Tokensizer;
+use aoi::typechecker;
+use libfuzzer_sys::fuzz_target;
+
+const STEP_LIMIT: usize = 10_000;
+const OUTPUT_LIMIT: usize = 1 << 24;
+
+fuzz_target!(|source: &str| {
+    if source.contains("scan") {
+        return;
+    }
+    let tokens = Tokensizer::new(source.to_string()).tokenize();
+    let Some(statements) = Parser::new(tokens).parse() else {
+        return;
+    };
+    if !typechecker::check(&statements).is_empty() {
+        return;
+    }
+    let mut interpreter = Interpreter::new();
+    interpreter.set_step_limit(STEP_LIMIT);
+    interpreter.set_output_limit(OUTPUT_LIMIT);
+    interpreter.interpret(&statements);
+});
@@ -0,0 +1,14 @@
+// Any input must parse (or fail to parse) and type-check without panicking.
+#![no_main]
+
+use aoi::parser::Parser;
+use aoi::token::Tokensizer;
+use aoi::typechecker;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    let tokens = Tokensizer::new(source.to_string()).tokenize();
+    if let Some(statements) = Parser::new(tokens).parse() {
+        typechecker::check(&statements);
+    }
+});
@@ -0,0 +1,9 @@
+// Any input must tokenize without panicking.
+#![no_main]
+
+use aoi::token::Tokensizer;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    Tokensizer::new(source.to_string()).tokenize();
+});
@@ -1,5 +1,6 @@
 use crate::expr::{Binary, Expr, ExprVisitor, Grouping, Literal, Unary, Variable};
-use crate::token::TokenLiteral;
+use crate::stmt::{Pattern, Stmt};
+use crate::token::{format_float, Token, TokenLiteral};
 
 pub struct AstPrinter;
 
@@ -15,6 +16,114 @@ impl AstPrinter {
         result.push(')');
         result
     }
+
+    /// Prints a statement in the same S-expression style, e.g. `(var x (+ 1 2))`.
+    pub fn print_stmt(&self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Expression { expression, .. } => expression.accept(self),
+            Stmt::Print { expression, .. } => self.parenthesize("write", &[expression]),
+            Stmt::Var {
+                name,
+                type_annotation,
+                initializer,
+                ..
+            } => {
+                let name = match type_annotation {
+                    Some(ty) => format!("var {}: {}", name.lexeme, ty.lexeme),
+                    None => format!("var {}", name.lexeme),
+                };
+                match initializer {
+                    Some(init) => self.parenthesize(&name, &[init]),
+                    None => format!("({})", name),
+                }
+            }
+            Stmt::Block { statements, .. } => self.statements("block", statements),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                let mut result = format!("(if {} {}", condition.accept(self), self.print_stmt(then_branch));
+                if let Some(else_branch) = else_branch {
+                    result.push(' ');
+                    result.push_str(&self.print_stmt(else_branch));
+                }
+                result.push(')');
+                result
+            }
+            Stmt::While {
+                condition, body, ..
+            } => format!("(while {} {})", condition.accept(self), self.print_stmt(body)),
+            Stmt::Input { name, .. } => format!("(scan {})", name.lexeme),
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => format!(
+                "(for {} {} {} {})",
+                initializer.as_ref().map_or("nil".to_string(), |init| self.print_stmt(init)),
+                condition.as_ref().map_or("nil".to_string(), |cond| cond.accept(self)),
+                increment.as_ref().map_or("nil".to_string(), |inc| inc.accept(self)),
+                self.print_stmt(body)
+            ),
+            Stmt::ForIn {
+                name, iterable, body, ..
+            } => format!(
+                "(for-in {} {} {})",
+                name.lexeme,
+                iterable.accept(self),
+                self.print_stmt(body)
+            ),
+            Stmt::Function {
+                name, params, body, ..
+            } => {
+                let params: Vec<&str> = params.iter().map(|p| p.lexeme.as_str()).collect();
+                self.statements(&format!("fun {} ({})", name.lexeme, params.join(" ")), body)
+            }
+            Stmt::Return { value, .. } => match value {
+                Some(value) => self.parenthesize("return", &[value]),
+                None => "(return)".to_string(),
+            },
+            Stmt::Yield { value, .. } => match value {
+                Some(value) => self.parenthesize("yield", &[value]),
+                None => "(yield)".to_string(),
+            },
+            Stmt::Match { subject, arms, .. } => {
+                let mut result = format!("(match {}", subject.accept(self));
+                for arm in arms {
+                    result.push_str(&format!(" (arm {}", self.pattern(&arm.pattern)));
+                    if let Some(guard) = &arm.guard {
+                        result.push_str(&format!(" (guard {})", guard.accept(self)));
+                    }
+                    result.push_str(&format!(" {})", self.print_stmt(&arm.body)));
+                }
+                result.push(')');
+                result
+            }
+        }
+    }
+
+    fn pattern(&self, pattern: &Pattern) -> String {
+        match pattern {
+            Pattern::Literal { value } => value.accept(self),
+            Pattern::Range { start, operator, end } => self.parenthesize(&operator.lexeme, &[start, end]),
+            Pattern::Wildcard { .. } => "_".to_string(),
+            Pattern::Binding { name } => name.lexeme.clone(),
+        }
+    }
+
+    fn statements(&self, name: &str, statements: &[Stmt]) -> String {
+        let mut result = format!("({}", name);
+        for stmt in statements {
+            result.push(' ');
+            result.push_str(&self.print_stmt(stmt));
+        }
+        result.push(')');
+        result
+    }
 }
 
 impl ExprVisitor for AstPrinter {
@@ -29,7 +138,9 @@ impl ExprVisitor for AstPrinter {
     fn visit_literal(&self, expr: &Literal) -> String {
         if let Some(token_literal) = expr.value.downcast_ref::<TokenLiteral>() {
             match token_literal {
-                TokenLiteral::Number(n) => return n.to_string(),
+                TokenLiteral::Integer(n) => return n.to_string(),
+                TokenLiteral::BigInteger(n) => return n.to_string(),
+                TokenLiteral::Number(n) => return format_float(*n),
                 TokenLiteral::String(s) => return format!("\"{}\"", s),
                 TokenLiteral::Boolean(b) => return b.to_string(),
                 TokenLiteral::Identifier(id) => return id.clone(),
@@ -40,9 +151,12 @@ impl ExprVisitor for AstPrinter {
         if let Some(b) = expr.value.downcast_ref::<bool>() {
             return b.to_string();
         }
-        if let Some(n) = expr.value.downcast_ref::<f64>() {
+        if let Some(n) = expr.value.downcast_ref::<i64>() {
             return n.to_string();
         }
+        if let Some(n) = expr.value.downcast_ref::<f64>() {
+            return format_float(*n);
+        }
         if let Some(s) = expr.value.downcast_ref::<String>() {
             return format!("\"{}\"", s);
         }
@@ -55,4 +169,41 @@ impl ExprVisitor for AstPrinter {
     fn visit_variable(&self, expr: &Variable) -> String {
         expr.name.lexeme.clone()
     }
+    fn visit_assign(&self, name: &Token, value: &Expr) -> String {
+        self.parenthesize(&format!("= {}", name.lexeme), &[value])
+    }
+    fn visit_if(&self, condition: &Expr, then_branch: &Expr, else_branch: Option<&Expr>) -> String {
+        match else_branch {
+            Some(else_branch) => self.parenthesize("if", &[condition, then_branch, else_branch]),
+            None => self.parenthesize("if", &[condition, then_branch]),
+        }
+    }
+    fn visit_logical(&self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        self.parenthesize(&operator.lexeme, &[left, right])
+    }
+    fn visit_call(&self, callee: &Expr, arguments: &[Expr]) -> String {
+        let mut expressions = vec![callee];
+        expressions.extend(arguments);
+        self.parenthesize("call", &expressions)
+    }
+    fn visit_compound_assign(&self, name: &Token, operator: &Token, value: &Expr) -> String {
+        self.parenthesize(&format!("{} {}", operator.lexeme, name.lexeme), &[value])
+    }
+    fn visit_increment(&self, name: &Token, operator: &Token, prefix: bool) -> String {
+        if prefix {
+            format!("({} {})", operator.lexeme, name.lexeme)
+        } else {
+            format!("({} {} postfix)", operator.lexeme, name.lexeme)
+        }
+    }
+    fn visit_interpolation(&self, parts: &[Expr]) -> String {
+        let parts: Vec<&Expr> = parts.iter().collect();
+        self.parenthesize("interpolate", &parts)
+    }
+    fn visit_range(&self, start: &Expr, operator: &Token, end: &Expr, step: Option<&Expr>) -> String {
+        match step {
+            Some(step) => self.parenthesize(&operator.lexeme, &[start, end, step]),
+            None => self.parenthesize(&operator.lexeme, &[start, end]),
+        }
+    }
 }
@@ -0,0 +1,107 @@
+// Line coverage built on `interpreter::Hook`. Every line that starts a statement (other
+// than a block) is executable; a line is covered once any statement on it has run.
+use crate::interpreter::{Frame, Hook};
+use crate::stmt::Stmt;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+pub struct Coverage {
+    hits: BTreeMap<usize, usize>, // executable line -> times it was reached
+}
+
+impl Coverage {
+    pub fn new(statements: &[Stmt]) -> Self {
+        let mut coverage = Coverage { hits: BTreeMap::new() };
+        coverage.collect(statements);
+        coverage
+    }
+
+    fn collect(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            self.collect_statement(stmt);
+        }
+    }
+
+    fn collect_statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block { statements, .. } => return self.collect(statements),
+            Stmt::If {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.collect_statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.collect_statement(else_branch);
+                }
+            }
+            Stmt::While { body, .. } | Stmt::ForIn { body, .. } => self.collect_statement(body),
+            Stmt::For {
+                initializer, body, ..
+            } => {
+                if let Some(init) = initializer {
+                    self.collect_statement(init);
+                }
+                self.collect_statement(body);
+            }
+            Stmt::Function { body, .. } => self.collect(body),
+            Stmt::Match { arms, .. } => {
+                for arm in arms {
+                    self.collect_statement(&arm.body);
+                }
+            }
+            _ => {}
+        }
+        self.hits.insert(stmt.line(), 0);
+    }
+
+    fn covered(&self) -> usize {
+        self.hits.values().filter(|hits| **hits > 0).count()
+    }
+
+    /// `Covered 7 of 9 lines (77.8%)`.
+    pub fn summary(&self) -> String {
+        let total = self.hits.len();
+        let percent = if total == 0 {
+            100.0
+        } else {
+            self.covered() as f64 * 100.0 / total as f64
+        };
+        format!("Covered {} of {} lines ({:.1}%)", self.covered(), total, percent)
+    }
+
+    /// The source with a gcov-style gutter: hit count, `#####` for lines never
+    /// reached, `-` for lines with nothing to execute.
+    pub fn listing(&self, source: &str) -> String {
+        let mut out = String::new();
+        for (index, text) in source.lines().enumerate() {
+            let gutter = match self.hits.get(&(index + 1)) {
+                Some(0) => "#####".to_string(),
+                Some(hits) => hits.to_string(),
+                None => "-".to_string(),
+            };
+            let _ = writeln!(out, "{:>9} | {:>4} | {}", gutter, index + 1, text);
+        }
+        out
+    }
+
+    /// An lcov tracefile for `path`, readable by `genhtml` and editor coverage views.
+    pub fn lcov(&self, path: &str) -> String {
+        let mut out = format!("TN:\nSF:{}\n", path);
+        for (line, hits) in &self.hits {
+            let _ = writeln!(out, "DA:{},{}", line, hits);
+        }
+        let _ = write!(out, "LF:{}\nLH:{}\nend_of_record\n", self.hits.len(), self.covered());
+        out
+    }
+}
+
+impl Hook for Coverage {
+    fn statement(&mut self, stmt: &Stmt, frames: &[Frame]) -> Result<(), String> {
+        // `if (x) write(x);` reaches its line once, not twice.
+        if frames.last().is_none_or(Frame::is_new_line) {
+            *self.hits.entry(stmt.line()).or_default() += 1;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,814 @@
+// Lossless concrete syntax tree. Unlike `Stmt`/`Expr`, every byte of the source
+// (whitespace, comments, stray characters) ends up somewhere in the tree, so
+// `parse(src).text() == src` always holds.
+use crate::parser::MAX_NESTING;
+use crate::token::{block_comment_len, Span, Token, TokenLiteral, TokenType, Tokensizer};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    Comment,
+    Unknown, // characters the tokenizer rejected, unterminated strings, ...
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+/// A token together with the trivia that precedes it. Trivia at the end of the
+/// file is attached to the EOF token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstToken {
+    pub token: Token,
+    pub leading: Vec<Trivia>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyntaxKind {
+    SourceFile,
+    VarDecl,
+    FunDecl,
+    ParamList,
+    TypeAnnotation,
+    Block,
+    IfStmt,
+    ElseClause,
+    WhileStmt,
+    ForStmt,
+    ExprStmt,
+    PrintStmt,
+    ScanStmt,
+    ReturnStmt,
+    YieldStmt,
+    MatchStmt,
+    MatchArm,
+    AssignExpr,
+    LogicalExpr,
+    BinaryExpr,
+    UnaryExpr,
+    IncrementExpr,
+    CallExpr,
+    ArgList,
+    GroupingExpr,
+    LiteralExpr,
+    InterpolationExpr,
+    RangeExpr,
+    NameExpr,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstElement {
+    Node(CstNode),
+    Token(CstToken),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<CstElement>,
+}
+
+impl CstNode {
+    fn new(kind: SyntaxKind) -> Self {
+        CstNode {
+            kind,
+            children: Vec::new(),
+        }
+    }
+
+    /// Reassembles the exact source text covered by this node.
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        self.write_text(&mut out);
+        out
+    }
+
+    fn write_text(&self, out: &mut String) {
+        for child in &self.children {
+            match child {
+                CstElement::Node(node) => node.write_text(out),
+                CstElement::Token(token) => {
+                    for trivia in &token.leading {
+                        out.push_str(&trivia.text);
+                    }
+                    out.push_str(&token.token.lexeme);
+                }
+            }
+        }
+    }
+
+    /// Indented dump of the tree, one node, token or trivia per line.
+    pub fn debug_tree(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, 0);
+        out
+    }
+
+    fn write_tree(&self, out: &mut String, depth: usize) {
+        let _ = writeln!(out, "{}{:?}", "  ".repeat(depth), self.kind);
+        for child in &self.children {
+            match child {
+                CstElement::Node(node) => node.write_tree(out, depth + 1),
+                CstElement::Token(token) => {
+                    for trivia in &token.leading {
+                        let _ = writeln!(out, "{}{:?} {:?}", "  ".repeat(depth + 1), trivia.kind, trivia.text);
+                    }
+                    let _ = writeln!(
+                        out,
+                        "{}{:?} {:?}",
+                        "  ".repeat(depth + 1),
+                        token.token.token_type,
+                        token.token.lexeme
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Tokenizes `source`, keeping whitespace and comments as leading trivia.
+pub fn tokenize_with_trivia(source: &str) -> Vec<CstToken> {
+    let tokens = Tokensizer::new(source.to_string()).tokenize();
+
+    let mut result = Vec::with_capacity(tokens.len());
+    let mut position = 0;
+    for token in tokens {
+        let start = token.span.start.clamp(position, source.len());
+        let leading = split_trivia(&source[position..start]);
+        position = token.span.end.clamp(start, source.len());
+        result.push(CstToken { token, leading });
+    }
+    result
+}
+
+// Splits the text between two tokens into whitespace, newline, comment and unknown pieces.
+fn split_trivia(text: &str) -> Vec<Trivia> {
+    let mut trivia: Vec<Trivia> = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let (kind, len) = if rest.starts_with("\r\n") {
+            (TriviaKind::Newline, 2)
+        } else if c == '\n' {
+            (TriviaKind::Newline, 1)
+        } else if rest.starts_with("//") {
+            (TriviaKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if rest.starts_with("/*") {
+            (TriviaKind::Comment, block_comment_len(rest).unwrap_or(rest.len()))
+        } else if c == ' ' || c == '\t' || c == '\r' {
+            let end = rest
+                .find(|c| c != ' ' && c != '\t' && c != '\r')
+                .unwrap_or(rest.len());
+            (TriviaKind::Whitespace, end)
+        } else {
+            (TriviaKind::Unknown, c.len_utf8())
+        };
+
+        let (piece, tail) = rest.split_at(len);
+        match trivia.last_mut() {
+            Some(last) if last.kind == kind && kind == TriviaKind::Unknown => last.text.push_str(piece),
+            _ => trivia.push(Trivia {
+                kind,
+                text: piece.to_string(),
+            }),
+        }
+        rest = tail;
+    }
+    trivia
+}
+
+/// A problem found while building the tree, located by byte offsets into the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Parses `source` into a lossless syntax tree. Never fails: anything the grammar
+/// doesn't expect is wrapped in an `Error` node.
+pub fn parse(source: &str) -> CstNode {
+    parse_with_errors(source).0
+}
+
+/// Like `parse`, but also reports what each `Error` node stands for, plus any
+/// characters the tokenizer had to skip.
+pub fn parse_with_errors(source: &str) -> (CstNode, Vec<SyntaxError>) {
+    let tokens = tokenize_with_trivia(source);
+
+    let mut errors = Vec::new();
+    for token in &tokens {
+        let leading: usize = token.leading.iter().map(|trivia| trivia.text.len()).sum();
+        let mut position = token.token.span.start.saturating_sub(leading);
+        for trivia in &token.leading {
+            let span = Span {
+                start: position,
+                end: position + trivia.text.len(),
+            };
+            if trivia.kind == TriviaKind::Unknown {
+                errors.push(SyntaxError {
+                    span,
+                    message: format!("Unexpected '{}'.", trivia.text.trim_end()),
+                });
+            } else if trivia.text.starts_with("/*") && block_comment_len(&trivia.text).is_none() {
+                errors.push(SyntaxError {
+                    span,
+                    message: "Unterminated block comment.".to_string(),
+                });
+            }
+            position += trivia.text.len();
+        }
+    }
+
+    let mut parser = CstParser {
+        tokens,
+        current: 0,
+        depth: 0,
+        gave_up: false,
+        errors,
+    };
+    let tree = parser.source_file();
+    let mut errors = parser.errors;
+    errors.sort_by_key(|err| err.span.start);
+    (tree, errors)
+}
+
+struct CstParser {
+    tokens: Vec<CstToken>,
+    current: usize,
+    depth: usize,  // current nesting, see MAX_NESTING
+    gave_up: bool, // MAX_NESTING was hit and the rest of the input put in one `Error` node
+    errors: Vec<SyntaxError>,
+}
+
+impl CstParser {
+    fn source_file(&mut self) -> CstNode {
+        let mut node = CstNode::new(SyntaxKind::SourceFile);
+        while !self.is_at_end() {
+            self.statement_into(&mut node);
+        }
+        // The EOF token carries the trailing trivia of the file.
+        let eof = self.tokens[self.current].clone();
+        node.children.push(CstElement::Token(eof));
+        node
+    }
+
+    // Parses one statement into `parent`, making sure at least one token is consumed.
+    fn statement_into(&mut self, parent: &mut CstNode) {
+        let before = self.current;
+        let stmt = self.nested(Self::statement);
+        parent.children.push(CstElement::Node(stmt));
+        if self.current == before && !self.is_at_end() {
+            self.unexpected();
+            let mut error = CstNode::new(SyntaxKind::Error);
+            self.bump(&mut error);
+            parent.children.push(CstElement::Node(error));
+        }
+    }
+
+    fn statement(&mut self) -> CstNode {
+        match self.peek_type() {
+            TokenType::VAR => self.var_declaration(),
+            TokenType::PRINT => {
+                let mut node = CstNode::new(SyntaxKind::PrintStmt);
+                self.bump(&mut node);
+                self.expect(&mut node, TokenType::LEFT_PAREN);
+                self.expression_into(&mut node);
+                self.expect(&mut node, TokenType::RIGHT_PAREN);
+                self.expect(&mut node, TokenType::SEMICOLON);
+                node
+            }
+            TokenType::LEFT_BRACE => self.block(),
+            TokenType::IF => {
+                let mut node = CstNode::new(SyntaxKind::IfStmt);
+                self.bump(&mut node);
+                self.condition_into(&mut node);
+                self.statement_into(&mut node);
+                if self.peek_type() == TokenType::ELSE {
+                    let mut else_clause = CstNode::new(SyntaxKind::ElseClause);
+                    self.bump(&mut else_clause);
+                    self.statement_into(&mut else_clause);
+                    node.children.push(CstElement::Node(else_clause));
+                }
+                node
+            }
+            TokenType::SCAN => {
+                let mut node = CstNode::new(SyntaxKind::ScanStmt);
+                self.bump(&mut node);
+                self.expect(&mut node, TokenType::LEFT_PAREN);
+                self.expect(&mut node, TokenType::IDENTIFIER);
+                self.expect(&mut node, TokenType::RIGHT_PAREN);
+                self.expect(&mut node, TokenType::SEMICOLON);
+                node
+            }
+            TokenType::WHILE => {
+                let mut node = CstNode::new(SyntaxKind::WhileStmt);
+                self.bump(&mut node);
+                self.condition_into(&mut node);
+                self.statement_into(&mut node);
+                node
+            }
+            TokenType::FUN => {
+                let mut node = CstNode::new(SyntaxKind::FunDecl);
+                self.bump(&mut node);
+                self.expect(&mut node, TokenType::IDENTIFIER);
+                let mut params = CstNode::new(SyntaxKind::ParamList);
+                self.expect(&mut params, TokenType::LEFT_PAREN);
+                while self.peek_type() == TokenType::IDENTIFIER {
+                    self.bump(&mut params);
+                    self.type_annotation_into(&mut params);
+                    if self.peek_type() != TokenType::COMMA {
+                        break;
+                    }
+                    self.bump(&mut params);
+                }
+                self.expect(&mut params, TokenType::RIGHT_PAREN);
+                node.children.push(CstElement::Node(params));
+                self.type_annotation_into(&mut node);
+                if self.peek_type() == TokenType::LEFT_BRACE {
+                    node.children.push(CstElement::Node(self.block()));
+                }
+                node
+            }
+            TokenType::RETURN | TokenType::YIELD => {
+                let kind = if self.peek_type() == TokenType::RETURN {
+                    SyntaxKind::ReturnStmt
+                } else {
+                    SyntaxKind::YieldStmt
+                };
+                let mut node = CstNode::new(kind);
+                self.bump(&mut node);
+                if self.peek_type() != TokenType::SEMICOLON {
+                    self.expression_into(&mut node);
+                }
+                self.expect(&mut node, TokenType::SEMICOLON);
+                node
+            }
+            TokenType::MATCH => {
+                let mut node = CstNode::new(SyntaxKind::MatchStmt);
+                self.bump(&mut node);
+                self.condition_into(&mut node);
+                self.expect(&mut node, TokenType::LEFT_BRACE);
+                while !matches!(self.peek_type(), TokenType::RIGHT_BRACE | TokenType::EOF) {
+                    let start = self.current;
+                    let mut arm = CstNode::new(SyntaxKind::MatchArm);
+                    // A pattern parses like the expression it looks like: `1..=9`, `-1`, `_`.
+                    self.expression_into(&mut arm);
+                    if self.peek_type() == TokenType::IF {
+                        self.bump(&mut arm);
+                        self.expression_into(&mut arm);
+                    }
+                    self.expect(&mut arm, TokenType::EQUAL_GREATER);
+                    self.statement_into(&mut arm);
+                    node.children.push(CstElement::Node(arm));
+                    if self.current == start {
+                        // Nothing fitted; skip the token so the loop moves on.
+                        self.unexpected();
+                        let mut error = CstNode::new(SyntaxKind::Error);
+                        self.bump(&mut error);
+                        node.children.push(CstElement::Node(error));
+                    }
+                }
+                self.expect(&mut node, TokenType::RIGHT_BRACE);
+                node
+            }
+            TokenType::FOR => {
+                let mut node = CstNode::new(SyntaxKind::ForStmt);
+                self.bump(&mut node);
+                self.expect(&mut node, TokenType::LEFT_PAREN);
+                if self.peek_type() == TokenType::IDENTIFIER && self.peek_next_type() == TokenType::IN {
+                    self.bump(&mut node);
+                    self.bump(&mut node);
+                    self.expression_into(&mut node);
+                    self.expect(&mut node, TokenType::RIGHT_PAREN);
+                    self.statement_into(&mut node);
+                    return node;
+                }
+                match self.peek_type() {
+                    TokenType::SEMICOLON => self.bump(&mut node),
+                    TokenType::VAR => node.children.push(CstElement::Node(self.var_declaration())),
+                    _ => node.children.push(CstElement::Node(self.expression_statement())),
+                }
+                if self.peek_type() != TokenType::SEMICOLON {
+                    self.expression_into(&mut node);
+                }
+                self.expect(&mut node, TokenType::SEMICOLON);
+                if self.peek_type() != TokenType::RIGHT_PAREN {
+                    self.expression_into(&mut node);
+                }
+                self.expect(&mut node, TokenType::RIGHT_PAREN);
+                self.statement_into(&mut node);
+                node
+            }
+            _ => self.expression_statement(),
+        }
+    }
+
+    fn var_declaration(&mut self) -> CstNode {
+        let mut node = CstNode::new(SyntaxKind::VarDecl);
+        self.bump(&mut node);
+        self.expect(&mut node, TokenType::IDENTIFIER);
+        self.type_annotation_into(&mut node);
+        if self.peek_type() == TokenType::EQUAL {
+            self.bump(&mut node);
+            self.expression_into(&mut node);
+        }
+        self.expect(&mut node, TokenType::SEMICOLON);
+        node
+    }
+
+    // Optional `: type`.
+    fn type_annotation_into(&mut self, parent: &mut CstNode) {
+        if self.peek_type() != TokenType::COLON {
+            return;
+        }
+        let mut node = CstNode::new(SyntaxKind::TypeAnnotation);
+        self.bump(&mut node);
+        match self.peek_type() {
+            TokenType::IDENTIFIER | TokenType::NIL | TokenType::FUN => self.bump(&mut node),
+            _ => {
+                self.missing("type name");
+                node.children.push(CstElement::Node(CstNode::new(SyntaxKind::Error)));
+            }
+        }
+        parent.children.push(CstElement::Node(node));
+    }
+
+    fn expression_statement(&mut self) -> CstNode {
+        let mut node = CstNode::new(SyntaxKind::ExprStmt);
+        self.expression_into(&mut node);
+        self.expect(&mut node, TokenType::SEMICOLON);
+        node
+    }
+
+    fn block(&mut self) -> CstNode {
+        let mut node = CstNode::new(SyntaxKind::Block);
+        self.bump(&mut node);
+        while self.peek_type() != TokenType::RIGHT_BRACE && !self.is_at_end() {
+            self.statement_into(&mut node);
+        }
+        self.expect(&mut node, TokenType::RIGHT_BRACE);
+        node
+    }
+
+    // `( expression )` after `if`, `while` and `match`.
+    fn condition_into(&mut self, node: &mut CstNode) {
+        self.expect(node, TokenType::LEFT_PAREN);
+        self.expression_into(node);
+        self.expect(node, TokenType::RIGHT_PAREN);
+    }
+
+    fn expression_into(&mut self, node: &mut CstNode) {
+        let expr = self.nested(Self::assignment);
+        node.children.push(CstElement::Node(expr));
+    }
+
+    fn assignment(&mut self) -> CstNode {
+        let target = self.logical(TokenType::OR);
+        if !matches!(
+            self.peek_type(),
+            TokenType::EQUAL
+                | TokenType::PLUS_EQUAL
+                | TokenType::MINUS_EQUAL
+                | TokenType::STAR_EQUAL
+                | TokenType::SLASH_EQUAL
+                | TokenType::PERCENT_EQUAL
+        ) {
+            return target;
+        }
+        // Only a plain name can be assigned to, as in `Parser::assignment`.
+        if !matches!(target.kind, SyntaxKind::NameExpr | SyntaxKind::Error) && !self.gave_up {
+            self.errors.push(SyntaxError {
+                span: self.tokens[self.current].token.span,
+                message: "Invalid assignment target.".to_string(),
+            });
+        }
+        let mut node = CstNode::new(SyntaxKind::AssignExpr);
+        node.children.push(CstElement::Node(target));
+        self.bump(&mut node);
+        let value = self.nested(Self::assignment);
+        node.children.push(CstElement::Node(value));
+        node
+    }
+
+    fn logical(&mut self, operator: TokenType) -> CstNode {
+        let operand = |parser: &mut CstParser| match operator {
+            TokenType::OR => parser.logical(TokenType::AND),
+            _ => parser.binary(0),
+        };
+        let mut left = operand(self);
+        let depth = self.depth;
+        while self.peek_type() == operator {
+            let mut node = CstNode::new(SyntaxKind::LogicalExpr);
+            node.children.push(CstElement::Node(left));
+            self.bump(&mut node);
+            node.children.push(CstElement::Node(self.chained(operand)));
+            left = node;
+        }
+        self.depth = depth;
+        left
+    }
+
+    // Bitwise, equality, comparison, shift, term and factor levels, from loosest to tightest.
+    fn binary(&mut self, level: usize) -> CstNode {
+        const LEVELS: [&[TokenType]; 8] = [
+            &[TokenType::PIPE],
+            &[TokenType::CARET],
+            &[TokenType::AMPERSAND],
+            &[TokenType::BANG_EQUAL, TokenType::EQUAL_EQUAL],
+            &[
+                TokenType::GREATER,
+                TokenType::GREATER_EQUAL,
+                TokenType::LESS,
+                TokenType::LESS_EQUAL,
+            ],
+            &[TokenType::LESS_LESS, TokenType::GREATER_GREATER],
+            &[TokenType::MINUS, TokenType::PLUS],
+            &[
+                TokenType::SLASH,
+                TokenType::STAR,
+                TokenType::PERCENT,
+                TokenType::TILDE_SLASH,
+            ],
+        ];
+        let operand = |parser: &mut CstParser| {
+            if level == 4 {
+                parser.range()
+            } else if level + 1 < LEVELS.len() {
+                parser.binary(level + 1)
+            } else {
+                parser.unary()
+            }
+        };
+
+        let mut left = operand(self);
+        let depth = self.depth;
+        while LEVELS[level].contains(&self.peek_type()) {
+            let mut node = CstNode::new(SyntaxKind::BinaryExpr);
+            node.children.push(CstElement::Node(left));
+            self.bump(&mut node);
+            node.children.push(CstElement::Node(self.chained(operand)));
+            left = node;
+        }
+        self.depth = depth;
+        left
+    }
+
+    // `a..b`, `a..=b`, optionally followed by `step c`; binds between comparison and shift.
+    fn range(&mut self) -> CstNode {
+        let start = self.binary(5);
+        if !matches!(self.peek_type(), TokenType::DOT_DOT | TokenType::DOT_DOT_EQUAL) {
+            return start;
+        }
+        let mut node = CstNode::new(SyntaxKind::RangeExpr);
+        node.children.push(CstElement::Node(start));
+        self.bump(&mut node);
+        node.children.push(CstElement::Node(self.binary(5)));
+        let current = &self.tokens[self.current].token;
+        if current.token_type == TokenType::IDENTIFIER && current.lexeme == "step" {
+            self.bump(&mut node);
+            node.children.push(CstElement::Node(self.binary(5)));
+        }
+        node
+    }
+
+    fn unary(&mut self) -> CstNode {
+        if matches!(self.peek_type(), TokenType::BANG | TokenType::MINUS | TokenType::TILDE) {
+            let mut node = CstNode::new(SyntaxKind::UnaryExpr);
+            self.bump(&mut node);
+            let operand = self.nested(Self::unary);
+            node.children.push(CstElement::Node(operand));
+            return node;
+        }
+        if matches!(self.peek_type(), TokenType::PLUS_PLUS | TokenType::MINUS_MINUS) {
+            let mut node = CstNode::new(SyntaxKind::IncrementExpr);
+            self.bump(&mut node);
+            let operand = self.nested(Self::unary);
+            node.children.push(CstElement::Node(operand));
+            return node;
+        }
+        let mut operand = self.call();
+        if matches!(self.peek_type(), TokenType::PLUS_PLUS | TokenType::MINUS_MINUS) {
+            let mut node = CstNode::new(SyntaxKind::IncrementExpr);
+            node.children.push(CstElement::Node(operand));
+            self.bump(&mut node);
+            operand = node;
+        }
+        // `**` is right-associative and binds tighter than a prefix operator on its left.
+        if self.peek_type() != TokenType::STAR_STAR {
+            return operand;
+        }
+        let mut node = CstNode::new(SyntaxKind::BinaryExpr);
+        node.children.push(CstElement::Node(operand));
+        self.bump(&mut node);
+        let exponent = self.nested(Self::unary);
+        node.children.push(CstElement::Node(exponent));
+        node
+    }
+
+    fn call(&mut self) -> CstNode {
+        let mut expr = self.primary();
+        let depth = self.depth;
+        while self.peek_type() == TokenType::LEFT_PAREN {
+            let mut node = CstNode::new(SyntaxKind::CallExpr);
+            node.children.push(CstElement::Node(expr));
+            if !self.deepen() {
+                node.children.push(CstElement::Node(self.give_up()));
+                expr = node;
+                break;
+            }
+            let mut args = CstNode::new(SyntaxKind::ArgList);
+            self.bump(&mut args);
+            if self.peek_type() != TokenType::RIGHT_PAREN {
+                loop {
+                    self.expression_into(&mut args);
+                    if self.peek_type() != TokenType::COMMA {
+                        break;
+                    }
+                    self.bump(&mut args);
+                }
+            }
+            self.expect(&mut args, TokenType::RIGHT_PAREN);
+            node.children.push(CstElement::Node(args));
+            expr = node;
+        }
+        self.depth = depth;
+        expr
+    }
+
+    fn primary(&mut self) -> CstNode {
+        let kind = match self.peek_type() {
+            TokenType::ERROR => {
+                let token = &self.tokens[self.current].token;
+                if let Some(TokenLiteral::String(message)) = &token.literal {
+                    self.errors.push(SyntaxError {
+                        span: token.span,
+                        message: message.clone(),
+                    });
+                }
+                SyntaxKind::Error
+            }
+            TokenType::NUMBER
+            | TokenType::STRING
+            | TokenType::TRUE
+            | TokenType::FALSE
+            | TokenType::NIL => SyntaxKind::LiteralExpr,
+            TokenType::IDENTIFIER => SyntaxKind::NameExpr,
+            TokenType::LEFT_PAREN => {
+                let mut node = CstNode::new(SyntaxKind::GroupingExpr);
+                self.bump(&mut node);
+                self.expression_into(&mut node);
+                self.expect(&mut node, TokenType::RIGHT_PAREN);
+                return node;
+            }
+            // The string parts carry the `${` and `}` around each expression.
+            TokenType::INTERPOLATION => {
+                let mut node = CstNode::new(SyntaxKind::InterpolationExpr);
+                self.bump(&mut node);
+                loop {
+                    self.expression_into(&mut node);
+                    if self.peek_type() != TokenType::INTERPOLATION {
+                        break;
+                    }
+                    self.bump(&mut node);
+                }
+                self.expect(&mut node, TokenType::INTERPOLATION_END);
+                return node;
+            }
+            // Leave closing tokens alone so the enclosing construct can recover.
+            TokenType::SEMICOLON | TokenType::RIGHT_PAREN | TokenType::RIGHT_BRACE | TokenType::EOF => {
+                self.missing("expression");
+                return CstNode::new(SyntaxKind::Error);
+            }
+            _ => {
+                self.unexpected();
+                SyntaxKind::Error
+            }
+        };
+        let mut node = CstNode::new(kind);
+        self.bump(&mut node);
+        node
+    }
+
+    // Runs `parse` one nesting level deeper, or gives up once MAX_NESTING is reached.
+    fn nested(&mut self, parse: impl FnOnce(&mut Self) -> CstNode) -> CstNode {
+        if !self.deepen() {
+            return self.give_up();
+        }
+        let node = parse(self);
+        self.depth -= 1;
+        node
+    }
+
+    // The right operand of `a + b + c` and friends. Each link nests the tree a level further,
+    // so callers restore `depth` once the chain ends, as with calls.
+    fn chained(&mut self, operand: impl FnOnce(&mut Self) -> CstNode) -> CstNode {
+        if !self.deepen() {
+            return self.give_up();
+        }
+        operand(self)
+    }
+
+    fn deepen(&mut self) -> bool {
+        if self.depth >= MAX_NESTING {
+            return false;
+        }
+        self.depth += 1;
+        true
+    }
+
+    // Reports the nesting once and puts the rest of the input in one `Error` node, so the tree
+    // stays lossless without getting any deeper. Everything after that would only add noise.
+    fn give_up(&mut self) -> CstNode {
+        if !self.gave_up {
+            self.errors.push(SyntaxError {
+                span: self.tokens[self.current].token.span,
+                message: "Too deeply nested.".to_string(),
+            });
+            self.gave_up = true;
+        }
+        let mut node = CstNode::new(SyntaxKind::Error);
+        while !self.is_at_end() {
+            self.bump(&mut node);
+        }
+        node
+    }
+
+    // Consumes the expected token, or records an empty `Error` node where it was missing.
+    fn expect(&mut self, node: &mut CstNode, token_type: TokenType) {
+        if self.peek_type() == token_type {
+            self.bump(node);
+        } else {
+            self.missing(&describe(token_type));
+            node.children.push(CstElement::Node(CstNode::new(SyntaxKind::Error)));
+        }
+    }
+
+    // Something should have come before the current token: point just past the previous one.
+    fn missing(&mut self, what: &str) {
+        if self.gave_up {
+            return;
+        }
+        let at = match self.current {
+            0 => self.tokens[0].token.span.start,
+            n => self.tokens[n - 1].token.span.end,
+        };
+        self.errors.push(SyntaxError {
+            span: Span { start: at, end: at },
+            message: format!("Expected {}.", what),
+        });
+    }
+
+    fn unexpected(&mut self) {
+        if self.gave_up {
+            return;
+        }
+        let token = &self.tokens[self.current].token;
+        self.errors.push(SyntaxError {
+            span: token.span,
+            message: format!("Unexpected '{}'.", token.lexeme),
+        });
+    }
+
+    fn bump(&mut self, node: &mut CstNode) {
+        if self.is_at_end() {
+            return;
+        }
+        node.children
+            .push(CstElement::Token(self.tokens[self.current].clone()));
+        self.current += 1;
+    }
+
+    fn peek_type(&self) -> TokenType {
+        self.tokens[self.current].token.token_type
+    }
+
+    fn peek_next_type(&self) -> TokenType {
+        self.tokens
+            .get(self.current + 1)
+            .map_or(TokenType::EOF, |t| t.token.token_type)
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek_type() == TokenType::EOF
+    }
+}
+
+// How a missing token is named in error messages.
+fn describe(token_type: TokenType) -> String {
+    let text = match token_type {
+        TokenType::LEFT_PAREN => "'('",
+        TokenType::RIGHT_PAREN => "')'",
+        TokenType::LEFT_BRACE => "'{'",
+        TokenType::RIGHT_BRACE | TokenType::INTERPOLATION_END => "'}'",
+        TokenType::SEMICOLON => "';'",
+        TokenType::COMMA => "','",
+        TokenType::IDENTIFIER => "a name",
+        other => return format!("{:?}", other).to_lowercase(),
+    };
+    text.to_string()
+}
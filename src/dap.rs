@@ -0,0 +1,301 @@
+// Debug Adapter Protocol over stdio, for editor integration. Messages use the same
+// `Content-Length` framing as the language server. The program runs on this thread, so
+// requests are only read before launch, while paused, and after the program ends.
+use crate::debugger::{lookup, scope_chain, Debugger, Frontend, Resume};
+use crate::interpreter::{describe_value, Frame, Interpreter};
+use crate::lsp::{read_message, write_message};
+use crate::parser::Parser;
+use crate::token::Tokensizer;
+use crate::typechecker;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+// `variablesReference` values pack a frame index and a scope depth.
+const SCOPES_PER_FRAME: usize = 1000;
+
+/// Runs one debug session. Returns the process exit code.
+pub fn serve<R: BufRead + 'static, W: Write + 'static>(reader: R, writer: W) -> i32 {
+    let debugger = Rc::new(RefCell::new(Debugger::new(Adapter {
+        reader,
+        writer,
+        seq: 1,
+        program: String::new(),
+        stop_on_entry: false,
+        configured: false,
+        disconnected: false,
+    })));
+
+    // Configuration: `initialize`, `launch`, breakpoints, then `configurationDone`.
+    loop {
+        let mut session = debugger.borrow_mut();
+        let Debugger {
+            frontend, breakpoints, ..
+        } = &mut *session;
+        if !frontend.next_request(&[], breakpoints) || frontend.disconnected {
+            return 0;
+        }
+        if frontend.configured {
+            break;
+        }
+    }
+
+    let (program, stop_on_entry) = {
+        let session = debugger.borrow();
+        (session.frontend.program.clone(), session.frontend.stop_on_entry)
+    };
+    if stop_on_entry {
+        debugger.borrow_mut().stop_on_entry();
+    }
+    let exit_code = match load(&program) {
+        Ok(statements) => {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_hook(debugger.clone());
+            interpreter.interpret(&statements);
+            0
+        }
+        Err(message) => {
+            debugger.borrow_mut().frontend.output(&message);
+            1
+        }
+    };
+
+    let mut session = debugger.borrow_mut();
+    let Debugger {
+        frontend, breakpoints, ..
+    } = &mut *session;
+    if !frontend.disconnected {
+        frontend.event("terminated", json!({}));
+        frontend.event("exited", json!({ "exitCode": exit_code }));
+        while frontend.next_request(&[], breakpoints) && !frontend.disconnected {}
+    }
+    exit_code
+}
+
+// Reads and type-checks the program the way `run_code` does.
+fn load(path: &str) -> Result<Vec<crate::stmt::Stmt>, String> {
+    let source = fs::read_to_string(path).map_err(|err| format!("{}: {}\n", path, err))?;
+    let tokens = Tokensizer::new(source).tokenize();
+    let mut parser = Parser::new(tokens);
+    let Some(statements) = parser.parse() else {
+        let mut message: String = parser
+            .errors()
+            .iter()
+            .map(|err| format!("[line {}] {}\n", err.line, err))
+            .collect();
+        message.push_str("Parsing failed due to syntax errors.\n");
+        return Err(message);
+    };
+    let type_errors = typechecker::check(&statements);
+    if !type_errors.is_empty() {
+        return Err(type_errors.iter().map(|err| format!("{}\n", err)).collect());
+    }
+    Ok(statements)
+}
+
+pub struct Adapter<R, W> {
+    reader: R,
+    writer: W,
+    seq: i64,
+    program: String,
+    stop_on_entry: bool,
+    configured: bool,
+    disconnected: bool,
+}
+
+impl<R: BufRead, W: Write> Adapter<R, W> {
+    fn send(&mut self, mut message: Value) {
+        message["seq"] = json!(self.seq);
+        self.seq += 1;
+        let _ = write_message(&mut self.writer, &message);
+    }
+
+    fn event(&mut self, event: &str, body: Value) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body
+        }));
+    }
+
+    fn fail(&mut self, request: &Value, message: &str) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message
+        }));
+    }
+
+    // Handles one request. Returns false once the client is gone.
+    fn next_request(&mut self, frames: &[Frame], breakpoints: &mut BTreeSet<usize>) -> bool {
+        match read_message(&mut self.reader) {
+            Ok(Some(request)) => {
+                self.handle(&request, frames, breakpoints);
+                true
+            }
+            _ => {
+                self.disconnected = true;
+                false
+            }
+        }
+    }
+
+    // Answers `request`; returns how to resume if it was a stepping command.
+    fn handle(&mut self, request: &Value, frames: &[Frame], breakpoints: &mut BTreeSet<usize>) -> Option<Resume> {
+        let arguments = &request["arguments"];
+        match request["command"].as_str().unwrap_or("") {
+            "initialize" => {
+                self.respond(request, json!({ "supportsConfigurationDoneRequest": true }));
+                self.event("initialized", json!({}));
+            }
+            "launch" => {
+                self.program = arguments["program"].as_str().unwrap_or("").to_string();
+                self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+                self.respond(request, json!({}));
+            }
+            "setBreakpoints" => {
+                breakpoints.clear();
+                let lines = arguments["breakpoints"].as_array().cloned().unwrap_or_default();
+                let verified: Vec<Value> = lines
+                    .iter()
+                    .filter_map(|bp| bp["line"].as_u64())
+                    .map(|line| {
+                        breakpoints.insert(line as usize);
+                        json!({ "verified": true, "line": line })
+                    })
+                    .collect();
+                self.respond(request, json!({ "breakpoints": verified }));
+            }
+            "setExceptionBreakpoints" => self.respond(request, json!({})),
+            "configurationDone" => {
+                self.configured = true;
+                self.respond(request, json!({}));
+            }
+            "threads" => self.respond(request, json!({ "threads": [{ "id": 1, "name": "main" }] })),
+            "stackTrace" => {
+                let stack: Vec<Value> = frames
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(id, frame)| {
+                        json!({
+                            "id": id,
+                            "name": frame.function,
+                            "line": frame.line,
+                            "column": 1,
+                            "source": { "path": self.program }
+                        })
+                    })
+                    .collect();
+                self.respond(request, json!({ "stackFrames": stack, "totalFrames": frames.len() }));
+            }
+            "scopes" => {
+                let id = arguments["frameId"].as_u64().unwrap_or(0) as usize;
+                let Some(frame) = frames.get(id) else {
+                    self.fail(request, "Unknown frame.");
+                    return None;
+                };
+                let chain = scope_chain(&frame.environment);
+                let scopes: Vec<Value> = (0..chain.len())
+                    .map(|depth| {
+                        let name = match depth {
+                            0 => "Locals".to_string(),
+                            _ if depth + 1 == chain.len() => "Globals".to_string(),
+                            _ => format!("Enclosing {}", depth),
+                        };
+                        json!({
+                            "name": name,
+                            "variablesReference": id * SCOPES_PER_FRAME + depth + 1,
+                            "expensive": false
+                        })
+                    })
+                    .collect();
+                self.respond(request, json!({ "scopes": scopes }));
+            }
+            "variables" => {
+                let reference = arguments["variablesReference"].as_u64().unwrap_or(0) as usize;
+                let scope = reference.checked_sub(1).and_then(|packed| {
+                    let frame = frames.get(packed / SCOPES_PER_FRAME)?;
+                    scope_chain(&frame.environment).get(packed % SCOPES_PER_FRAME).cloned()
+                });
+                let variables: Vec<Value> = scope
+                    .map(|scope| scope.borrow().variables())
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|(name, value)| json!({ "name": name, "value": describe_value(value), "variablesReference": 0 }))
+                    .collect();
+                self.respond(request, json!({ "variables": variables }));
+            }
+            "evaluate" => {
+                let name = arguments["expression"].as_str().unwrap_or("").trim();
+                let frame = match arguments["frameId"].as_u64() {
+                    Some(id) => frames.get(id as usize),
+                    None => frames.last(),
+                };
+                match frame.and_then(|frame| lookup(&frame.environment, name)) {
+                    Some(value) => self.respond(request, json!({ "result": value, "variablesReference": 0 })),
+                    None => self.fail(request, &format!("Undefined variable '{}'.", name)),
+                }
+            }
+            "continue" => {
+                self.respond(request, json!({ "allThreadsContinued": true }));
+                return Some(Resume::Continue);
+            }
+            "next" => {
+                self.respond(request, json!({}));
+                return Some(Resume::StepOver);
+            }
+            "stepIn" => {
+                self.respond(request, json!({}));
+                return Some(Resume::StepIn);
+            }
+            "stepOut" => {
+                self.respond(request, json!({}));
+                return Some(Resume::StepOut);
+            }
+            "disconnect" | "terminate" => {
+                self.disconnected = true;
+                self.respond(request, json!({}));
+                return Some(Resume::Quit);
+            }
+            command => self.fail(request, &format!("Unsupported request '{}'.", command)),
+        }
+        None
+    }
+}
+
+impl<R: BufRead, W: Write> Frontend for Adapter<R, W> {
+    fn stopped(&mut self, reason: &str, frames: &[Frame], breakpoints: &mut BTreeSet<usize>) -> Resume {
+        self.event(
+            "stopped",
+            json!({ "reason": reason, "threadId": 1, "allThreadsStopped": true }),
+        );
+        loop {
+            let request = match read_message(&mut self.reader) {
+                Ok(Some(request)) => request,
+                _ => {
+                    self.disconnected = true;
+                    return Resume::Quit;
+                }
+            };
+            if let Some(resume) = self.handle(&request, frames, breakpoints) {
+                return resume;
+            }
+        }
+    }
+
+    fn output(&mut self, text: &str) {
+        self.event("output", json!({ "category": "stdout", "output": text }));
+    }
+}
@@ -0,0 +1,244 @@
+// Step debugger built on `interpreter::Hook`. `Debugger` decides where execution pauses
+// (breakpoints and stepping); a `Frontend` decides what happens while it is paused. The
+// interactive console lives here, the Debug Adapter Protocol front end in `dap.rs`.
+use crate::environment::Environment;
+use crate::interpreter::{describe_value, Frame, Hook};
+use crate::stmt::Stmt;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resume {
+    Continue,
+    StepIn,
+    StepOver,
+    StepOut,
+    Quit,
+}
+
+pub trait Frontend {
+    /// Execution is paused before a statement on `frames.last().line`; returns how to go on.
+    fn stopped(&mut self, reason: &str, frames: &[Frame], breakpoints: &mut BTreeSet<usize>) -> Resume;
+    fn output(&mut self, text: &str);
+}
+
+pub struct Debugger<F> {
+    pub breakpoints: BTreeSet<usize>,
+    pub frontend: F,
+    resume: Resume,
+    depth: usize, // call depth when the last step began
+    started: bool,
+}
+
+impl<F: Frontend> Debugger<F> {
+    pub fn new(frontend: F) -> Self {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            frontend,
+            resume: Resume::Continue,
+            depth: 1,
+            started: false,
+        }
+    }
+
+    /// Pause before the first statement instead of running to a breakpoint.
+    pub fn stop_on_entry(&mut self) {
+        self.resume = Resume::StepIn;
+    }
+
+    fn stop_reason(&self, stmt: &Stmt, frames: &[Frame]) -> Option<&'static str> {
+        let frame = frames.last()?;
+        // Statements nested on the line we already stopped at don't count as a new stop.
+        if !frame.is_new_line() {
+            return None;
+        }
+        if self.breakpoints.contains(&stmt.line()) {
+            return Some("breakpoint");
+        }
+        let depth = frames.len();
+        match self.resume {
+            Resume::StepIn if !self.started => Some("entry"),
+            Resume::StepIn => Some("step"),
+            Resume::StepOver if depth <= self.depth => Some("step"),
+            Resume::StepOut if depth < self.depth => Some("step"),
+            _ => None,
+        }
+    }
+}
+
+impl<F: Frontend> Hook for Debugger<F> {
+    fn statement(&mut self, stmt: &Stmt, frames: &[Frame]) -> Result<(), String> {
+        if let Some(reason) = self.stop_reason(stmt, frames) {
+            self.started = true;
+            self.resume = self.frontend.stopped(reason, frames, &mut self.breakpoints);
+            self.depth = frames.len();
+        }
+        self.started = true;
+        if self.resume == Resume::Quit {
+            return Err("Execution stopped by debugger.".to_string());
+        }
+        Ok(())
+    }
+
+    fn output(&mut self, text: &str) {
+        self.frontend.output(text);
+    }
+}
+
+/// Looks `name` up through a scope chain, innermost first.
+pub fn lookup(environment: &Rc<RefCell<Environment>>, name: &str) -> Option<String> {
+    scope_chain(environment).iter().find_map(|scope| {
+        scope
+            .borrow()
+            .variables()
+            .into_iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| describe_value(&value))
+    })
+}
+
+/// `environment` followed by each enclosing scope out to the globals.
+pub fn scope_chain(environment: &Rc<RefCell<Environment>>) -> Vec<Rc<RefCell<Environment>>> {
+    let mut chain = vec![environment.clone()];
+    while let Some(enclosing) = chain.last().and_then(|scope| scope.borrow().enclosing()) {
+        chain.push(enclosing);
+    }
+    chain
+}
+
+const HELP: &str = "\
+Commands:
+  break <line>, b     set a breakpoint (no line: list breakpoints)
+  delete <line>, d    remove a breakpoint
+  continue, c         run to the next breakpoint
+  step, s             step into calls
+  next, n             step over calls
+  out, o              run until the current function returns
+  backtrace, bt       show the call stack
+  print <name>, p     show a variable
+  env                 show every scope, innermost first
+  list, l             show the source around the current line
+  quit, q             stop the program";
+
+/// Line-oriented debugger console, e.g. on stdin/stdout.
+pub struct Console<R, W> {
+    input: R,
+    out: W,
+    source: Vec<String>,
+}
+
+impl<R: BufRead, W: Write> Console<R, W> {
+    pub fn new(input: R, out: W, source: &str) -> Self {
+        Console {
+            input,
+            out,
+            source: source.lines().map(str::to_string).collect(),
+        }
+    }
+
+    pub fn message(&mut self, text: &str) {
+        let _ = writeln!(self.out, "{}", text);
+    }
+
+    fn source_line(&self, line: usize) -> &str {
+        line.checked_sub(1)
+            .and_then(|index| self.source.get(index))
+            .map_or("", String::as_str)
+    }
+
+    fn list(&mut self, current: usize) {
+        let first = current.saturating_sub(3).max(1);
+        let last = (current + 3).min(self.source.len());
+        for line in first..=last {
+            let marker = if line == current { "->" } else { "  " };
+            let text = format!("{} {:>4} {}", marker, line, self.source_line(line));
+            self.message(&text);
+        }
+    }
+
+    fn environment(&mut self, frame: &Frame) {
+        let chain = scope_chain(&frame.environment);
+        for (depth, scope) in chain.iter().enumerate() {
+            let name = if depth + 1 == chain.len() { "globals".to_string() } else { format!("scope {}", depth) };
+            self.message(&format!("{}:", name));
+            for (variable, value) in scope.borrow().variables() {
+                self.message(&format!("  {} = {}", variable, describe_value(&value)));
+            }
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Frontend for Console<R, W> {
+    fn stopped(&mut self, reason: &str, frames: &[Frame], breakpoints: &mut BTreeSet<usize>) -> Resume {
+        let frame = frames.last().expect("paused with an empty call stack");
+        let text = format!(
+            "Stopped ({}) at line {} in {}: {}",
+            reason,
+            frame.line,
+            frame.function,
+            self.source_line(frame.line).trim()
+        );
+        self.message(&text);
+
+        loop {
+            let _ = write!(self.out, "(aoi-debug) ");
+            let _ = self.out.flush();
+            let mut command = String::new();
+            if self.input.read_line(&mut command).unwrap_or(0) == 0 {
+                return Resume::Quit;
+            }
+            let mut words = command.split_whitespace();
+            let verb = words.next().unwrap_or("");
+            let argument = words.next();
+            match verb {
+                "" => {}
+                "c" | "continue" => return Resume::Continue,
+                "s" | "step" => return Resume::StepIn,
+                "n" | "next" => return Resume::StepOver,
+                "o" | "out" | "finish" => return Resume::StepOut,
+                "q" | "quit" => return Resume::Quit,
+                "b" | "break" | "d" | "delete" => {
+                    let Some(argument) = argument else {
+                        let lines: Vec<String> = breakpoints.iter().map(usize::to_string).collect();
+                        self.message(&format!("Breakpoints: {}", if lines.is_empty() { "none".to_string() } else { lines.join(", ") }));
+                        continue;
+                    };
+                    match argument.parse::<usize>() {
+                        Ok(line) if verb.starts_with('b') => {
+                            breakpoints.insert(line);
+                            self.message(&format!("Breakpoint set at line {}.", line));
+                        }
+                        Ok(line) => {
+                            breakpoints.remove(&line);
+                            self.message(&format!("Breakpoint removed from line {}.", line));
+                        }
+                        Err(_) => self.message(&format!("Not a line number: '{}'.", argument)),
+                    }
+                }
+                "bt" | "backtrace" | "where" => {
+                    for (depth, frame) in frames.iter().rev().enumerate() {
+                        self.message(&format!("#{} {} at line {}", depth, frame.function, frame.line));
+                    }
+                }
+                "p" | "print" => match argument {
+                    Some(name) => match lookup(&frame.environment, name) {
+                        Some(value) => self.message(&format!("{} = {}", name, value)),
+                        None => self.message(&format!("Undefined variable '{}'.", name)),
+                    },
+                    None => self.message("Usage: print <name>"),
+                },
+                "env" | "locals" => self.environment(frame),
+                "l" | "list" => self.list(frame.line),
+                "h" | "help" => self.message(HELP),
+                _ => self.message(&format!("Unknown command '{}'. Type 'help' for a list.", verb)),
+            }
+        }
+    }
+
+    fn output(&mut self, text: &str) {
+        let _ = write!(self.out, "{}", text);
+        let _ = self.out.flush();
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::RuntimeError;
 use crate::token::Token;
+use num_bigint::BigInt;
 use std::any::Any;
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -16,7 +17,11 @@ impl Clone for Environment {
     fn clone(&self) -> Self {
         let mut cloned_values = HashMap::new();
         for (key, value) in &self.values {
-            let cloned_value = if let Some(v) = value.downcast_ref::<f64>() {
+            let cloned_value = if let Some(v) = value.downcast_ref::<i64>() {
+                Arc::new(*v) as Arc<dyn Any + Send + Sync>
+            } else if let Some(v) = value.downcast_ref::<BigInt>() {
+                Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
+            } else if let Some(v) = value.downcast_ref::<f64>() {
                 Arc::new(*v) as Arc<dyn Any + Send + Sync>
             } else if let Some(v) = value.downcast_ref::<String>() {
                 Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
@@ -43,14 +48,26 @@ impl Environment {
         }
     }
 
-    /// Creates a new environment with an enclosing scope (nested scope).
-
     /// Defines a new variable or updates an existing one in the current scope.
     pub fn define(&mut self, name: String, value: Arc<dyn Any + Send + Sync>) {
         self.values.insert(name, value);
     }
 
-    /// Defines a built-in (native) function in the environment.
+    /// Variables defined directly in this scope, sorted by name.
+    pub fn variables(&self) -> Vec<(String, Arc<dyn Any + Send + Sync>)> {
+        let mut variables: Vec<_> = self
+            .values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        variables.sort_by(|a, b| a.0.cmp(&b.0));
+        variables
+    }
+
+    /// The scope this one is nested in, if any.
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
 
     /// Retrieves the value of a variable.
     pub fn get(&self, name: &Token) -> Result<Arc<dyn Any + Send + Sync>, RuntimeError> {
@@ -1,26 +1,25 @@
 use crate::token::Token;
 #[warn(dead_code)]
-
 pub fn error(line: usize, message: &str, context: &str) {
     eprintln!(
         "[line {}] Error: {}\n{}\n{}^",
         line,
         message,
         context,
-        " ".repeat(context.len())
+        " ".repeat(context.chars().count())
     );
 }
 
 
 pub struct RuntimeError {
-    pub _token: Token,
+    pub _token: Box<Token>,
     pub _message: String,
 }
 
 impl RuntimeError {
     pub fn new(token: &Token, _message: String) -> Self {
         RuntimeError {
-            _token: token.clone(),
+            _token: Box::new(token.clone()),
             _message,
         }
     }
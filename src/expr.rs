@@ -2,13 +2,20 @@
 use crate::token::Token;
 use std::any::Any;
 #[allow(dead_code)]
-
 pub trait ExprVisitor {
     fn visit_binary(&self, expr: &Binary) -> String;
     fn visit_grouping(&self, expr: &Grouping) -> String;
     fn visit_literal(&self, expr: &Literal) -> String;
     fn visit_unary(&self, expr: &Unary) -> String;
     fn visit_variable(&self, expr: &Variable) -> String;
+    fn visit_assign(&self, name: &Token, value: &Expr) -> String;
+    fn visit_if(&self, condition: &Expr, then_branch: &Expr, else_branch: Option<&Expr>) -> String;
+    fn visit_logical(&self, left: &Expr, operator: &Token, right: &Expr) -> String;
+    fn visit_call(&self, callee: &Expr, arguments: &[Expr]) -> String;
+    fn visit_compound_assign(&self, name: &Token, operator: &Token, value: &Expr) -> String;
+    fn visit_increment(&self, name: &Token, operator: &Token, prefix: bool) -> String;
+    fn visit_interpolation(&self, parts: &[Expr]) -> String;
+    fn visit_range(&self, start: &Expr, operator: &Token, end: &Expr, step: Option<&Expr>) -> String;
 }
 // pub trait Expr {
 //     fn accept<T>(&self, visitor: & ExprVisitor<T>) -> T;
@@ -21,7 +28,10 @@ pub enum Expr {
     Literal(Literal),
     Unary(Unary),
     Variable(Variable),
-    Assign(String, Box<Expr>), // Represents variable assignment
+    Assign {
+        name: Token,
+        value: Box<Expr>,
+    }, // Represents variable assignment
     If {
         condition: Box<Expr>,
         then_branch: Box<Expr>,
@@ -35,6 +45,26 @@ pub enum Expr {
     Call {
         callee: Box<Expr>,
         arguments: Vec<Expr>,
+        paren: Token, // closing ')', marks the end of the call
+    },
+    CompoundAssign {
+        name: Token,
+        operator: Token, // `+=`, `-=`, `*=`, `/=` or `%=`
+        value: Box<Expr>,
+    },
+    Increment {
+        name: Token,
+        operator: Token, // `++` or `--`
+        prefix: bool,    // `++x` evaluates to the new value, `x++` to the old one
+    },
+    Interpolation {
+        parts: Vec<Expr>, // string literals alternating with the `${...}` expressions
+    },
+    Range {
+        start: Box<Expr>,
+        operator: Token, // `..` leaves `end` out, `..=` takes it in
+        end: Box<Expr>,
+        step: Option<Box<Expr>>,
     },
 }
 
@@ -49,23 +79,34 @@ pub struct Binary {
 #[derive(Clone)]
 pub struct Grouping {
     pub expression: Box<Expr>,
+    pub span: Span, // includes both parentheses
 }
 
 
 
-use crate::token::TokenLiteral;
+use crate::token::{Span, TokenLiteral};
+use serde::ser::{Serialize, SerializeMap, Serializer};
 use std::fmt::Debug;
 use std::sync::Arc; // Import TokenLiteral
 
 #[derive(Clone)]
 pub struct Literal {
     pub value: Arc<dyn Any + Send + Sync>,
+    pub span: Span,
 }
 
 impl Literal {
     pub fn new(value: TokenLiteral) -> Self {
         Literal {
             value: Arc::new(value), // Store TokenLiteral directly
+            span: Span::default(),
+        }
+    }
+
+    pub fn with_span(value: TokenLiteral, span: Span) -> Self {
+        Literal {
+            span,
+            ..Literal::new(value)
         }
     }
 }
@@ -99,43 +140,178 @@ impl Expr {
             Expr::Literal(l) => visitor.visit_literal(l),
             Expr::Unary(u) => visitor.visit_unary(u),
             Expr::Variable(v) => visitor.visit_variable(v),
-            Expr::Assign(name, _expr) => format!("Assign({}, ...)", name),
+            Expr::Assign { name, value } => visitor.visit_assign(name, value),
+            Expr::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => visitor.visit_if(condition, then_branch, else_branch.as_deref()),
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => visitor.visit_logical(left, operator, right),
+            Expr::Call {
+                callee, arguments, ..
+            } => visitor.visit_call(callee, arguments),
+            Expr::CompoundAssign {
+                name,
+                operator,
+                value,
+            } => visitor.visit_compound_assign(name, operator, value),
+            Expr::Increment {
+                name,
+                operator,
+                prefix,
+            } => visitor.visit_increment(name, operator, *prefix),
+            Expr::Interpolation { parts } => visitor.visit_interpolation(parts),
+            Expr::Range {
+                start,
+                operator,
+                end,
+                step,
+            } => visitor.visit_range(start, operator, end, step.as_deref()),
+        }
+    }
+}
+
+impl Expr {
+    /// Byte range covered by the expression in the source.
+    pub fn span(&self) -> Span {
+        let join = |start: Span, end: Span| Span {
+            start: start.start,
+            end: end.end,
+        };
+        match self {
+            Expr::Binary(b) => join(b.left.span(), b.right.span()),
+            Expr::Grouping(g) => g.span,
+            Expr::Literal(l) => l.span,
+            Expr::Unary(u) => join(u.operator.span, u.right.span()),
+            Expr::Variable(v) => v.name.span,
+            Expr::Assign { name, value } => join(name.span, value.span()),
+            Expr::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => join(
+                condition.span(),
+                else_branch.as_ref().unwrap_or(then_branch).span(),
+            ),
+            Expr::Logical { left, right, .. } => join(left.span(), right.span()),
+            Expr::Call { callee, paren, .. } => join(callee.span(), paren.span),
+            Expr::CompoundAssign { name, value, .. } => join(name.span, value.span()),
+            Expr::Increment {
+                name,
+                operator,
+                prefix: true,
+            } => join(operator.span, name.span),
+            Expr::Increment { name, operator, .. } => join(name.span, operator.span),
+            Expr::Interpolation { parts } => join(parts[0].span(), parts[parts.len() - 1].span()),
+            Expr::Range { start, end, step, .. } => {
+                join(start.span(), step.as_ref().unwrap_or(end).span())
+            }
+        }
+    }
+}
+
+// Serialized as `{"type": "<variant>", ...fields, "span": {...}}` for external tooling.
+impl Serialize for Expr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        match self {
+            Expr::Binary(b) => {
+                map.serialize_entry("type", "Binary")?;
+                map.serialize_entry("left", &b.left)?;
+                map.serialize_entry("operator", &b.operator)?;
+                map.serialize_entry("right", &b.right)?;
+            }
+            Expr::Grouping(g) => {
+                map.serialize_entry("type", "Grouping")?;
+                map.serialize_entry("expression", &g.expression)?;
+            }
+            Expr::Literal(l) => {
+                map.serialize_entry("type", "Literal")?;
+                map.serialize_entry("value", &l.value.downcast_ref::<TokenLiteral>())?;
+            }
+            Expr::Unary(u) => {
+                map.serialize_entry("type", "Unary")?;
+                map.serialize_entry("operator", &u.operator)?;
+                map.serialize_entry("right", &u.right)?;
+            }
+            Expr::Variable(v) => {
+                map.serialize_entry("type", "Variable")?;
+                map.serialize_entry("name", &v.name)?;
+            }
+            Expr::Assign { name, value } => {
+                map.serialize_entry("type", "Assign")?;
+                map.serialize_entry("name", name)?;
+                map.serialize_entry("value", value)?;
+            }
             Expr::If {
                 condition,
                 then_branch,
                 else_branch,
             } => {
-                format!(
-                    "If {{ {}, {}, {} }}",
-                    condition.accept(visitor),
-                    then_branch.accept(visitor),
-                    else_branch
-                        .as_ref()
-                        .map_or("None".to_string(), |e| e.accept(visitor))
-                )
+                map.serialize_entry("type", "If")?;
+                map.serialize_entry("condition", condition)?;
+                map.serialize_entry("then_branch", then_branch)?;
+                map.serialize_entry("else_branch", else_branch)?;
             }
             Expr::Logical {
                 left,
                 operator,
                 right,
             } => {
-                format!(
-                    "Logical {{ {}, {:?}, {} }}",
-                    left.accept(visitor),
-                    operator,
-                    right.accept(visitor)
-                )
+                map.serialize_entry("type", "Logical")?;
+                map.serialize_entry("left", left)?;
+                map.serialize_entry("operator", operator)?;
+                map.serialize_entry("right", right)?;
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                map.serialize_entry("type", "Call")?;
+                map.serialize_entry("callee", callee)?;
+                map.serialize_entry("arguments", arguments)?;
+            }
+            Expr::CompoundAssign {
+                name,
+                operator,
+                value,
+            } => {
+                map.serialize_entry("type", "CompoundAssign")?;
+                map.serialize_entry("name", name)?;
+                map.serialize_entry("operator", operator)?;
+                map.serialize_entry("value", value)?;
             }
-            Expr::Call { callee, arguments } => {
-                format!(
-                    "Call {{ {}, {:?} }}",
-                    callee.accept(visitor),
-                    arguments
-                        .iter()
-                        .map(|e| e.accept(visitor))
-                        .collect::<Vec<String>>()
-                )
+            Expr::Increment {
+                name,
+                operator,
+                prefix,
+            } => {
+                map.serialize_entry("type", "Increment")?;
+                map.serialize_entry("name", name)?;
+                map.serialize_entry("operator", operator)?;
+                map.serialize_entry("prefix", prefix)?;
+            }
+            Expr::Interpolation { parts } => {
+                map.serialize_entry("type", "Interpolation")?;
+                map.serialize_entry("parts", parts)?;
+            }
+            Expr::Range {
+                start,
+                operator,
+                end,
+                step,
+            } => {
+                map.serialize_entry("type", "Range")?;
+                map.serialize_entry("start", start)?;
+                map.serialize_entry("operator", operator)?;
+                map.serialize_entry("end", end)?;
+                map.serialize_entry("step", step)?;
             }
         }
+        map.serialize_entry("span", &self.span())?;
+        map.end()
     }
 }
@@ -0,0 +1,566 @@
+use crate::expr::{Binary, Expr, ExprVisitor, Grouping, Literal, Unary, Variable};
+use crate::parser::Parser;
+use crate::stmt::{Pattern, Stmt};
+use crate::token::{format_float, Comment, Token, TokenLiteral, TokenType, Tokensizer};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+const INDENT: &str = "    ";
+
+/// Formats a whole Aoi source file into its canonical layout.
+/// Comments are kept, and runs of blank lines between statements collapse to one.
+pub fn format_source(source: &str) -> Result<String, String> {
+    let mut tokenizer = Tokensizer::new(source.to_string());
+    let tokens = tokenizer.tokenize();
+    let comments = tokenizer.comments().to_vec();
+    // Formatting from tokens that lost part of the source would silently delete it.
+    if tokenizer.had_error() || tokens.iter().any(|token| token.token_type == TokenType::ERROR) {
+        return Err("Tokenizing failed due to invalid input.".to_string());
+    }
+
+    let mut parser = Parser::new(tokens.clone());
+    let statements = parser
+        .parse()
+        .ok_or_else(|| "Parsing failed due to syntax errors.".to_string())?;
+
+    // A dry run finds the tokens the formatter writes itself, so block comments in front of
+    // one of them can travel with it.
+    let mut dry_run = Formatter::new(source, comments.clone());
+    dry_run.program(&statements);
+    let mut formatter = Formatter::new(source, comments);
+    formatter.attach_comments(&tokens, &dry_run.anchors.into_inner());
+    // Comments are only written between statements and after a line's last token. One inside
+    // an expression or a header such as `for (...)` would end up somewhere else, so refuse.
+    for comment in &formatter.comments {
+        let next = tokens.partition_point(|token| token.span.start < comment.span.end);
+        let in_place = tokens
+            .get(next)
+            .is_none_or(|token| token.token_type == TokenType::EOF || dry_run.stops.contains(&token.span.start));
+        if !in_place {
+            return Err(format!(
+                "The comment on line {} can't be kept in place; move it next to a statement.",
+                comment.line
+            ));
+        }
+    }
+    formatter.program(&statements);
+    Ok(formatter.output)
+}
+
+/// Pretty-printer for the whole `Stmt`/`Expr` tree.
+pub struct Formatter<'a> {
+    source: &'a str,
+    lines: Vec<&'a str>,
+    comments: Vec<Comment>,
+    next_comment: usize,
+    attached: RefCell<HashMap<usize, String>>, // block comments to write before the token at a byte offset
+    anchors: RefCell<HashSet<usize>>,          // offsets of every token asked for in `attached`
+    stops: HashSet<usize>,                     // offsets `leading_comments` was called with
+    output: String,
+    indent: usize,
+    first_in_block: bool, // suppresses blank lines right after an opening brace
+}
+
+impl<'a> Formatter<'a> {
+    pub fn new(source: &'a str, comments: Vec<Comment>) -> Self {
+        Formatter {
+            source,
+            lines: source.lines().collect(),
+            comments,
+            next_comment: 0,
+            attached: RefCell::new(HashMap::new()),
+            anchors: RefCell::new(HashSet::new()),
+            stops: HashSet::new(),
+            output: String::new(),
+            indent: 0,
+            first_in_block: true,
+        }
+    }
+
+    pub fn expression(&self, expr: &Expr) -> String {
+        format!("{}{}", self.attached(expr.span().start), expr.accept(self))
+    }
+
+    pub fn statement(&mut self, stmt: &Stmt) {
+        self.leading_comments(stmt.span().start);
+        self.blank_line_before(stmt.line());
+        let prefix = self.attached(stmt.span().start);
+        self.statement_with_prefix(stmt, &prefix);
+    }
+
+    fn program(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            self.statement(stmt);
+        }
+        self.leading_comments(usize::MAX);
+    }
+
+    // Moves each block comment with code after it on the same line, such as `/* x */ a`, out
+    // of the line-based comment list so it is written right before that code. Only tokens in
+    // `anchors` qualify; comments before `)`, `;` and the like keep their line.
+    fn attach_comments(&mut self, tokens: &[Token], anchors: &HashSet<usize>) {
+        let mut attached = HashMap::new();
+        self.comments.retain(|comment| {
+            let next = tokens.partition_point(|token| token.span.start < comment.span.end);
+            let Some(token) = tokens.get(next) else {
+                return true;
+            };
+            let same_line = !self.source[comment.span.end..token.span.start].contains('\n');
+            if !comment.text.starts_with("/*") || !same_line || !anchors.contains(&token.span.start) {
+                return true;
+            }
+            attached
+                .entry(token.span.start)
+                .and_modify(|text: &mut String| text.push_str(&format!(" {}", comment.text)))
+                .or_insert_with(|| comment.text.clone());
+            false
+        });
+        self.attached = RefCell::new(attached);
+    }
+
+    // The comments attached to the token at `offset`, each followed by a space. Taking them
+    // means an expression and its first operand, which start at the same token, only write
+    // them once.
+    fn attached(&self, offset: usize) -> String {
+        self.anchors.borrow_mut().insert(offset);
+        match self.attached.borrow_mut().remove(&offset) {
+            Some(text) => format!("{} ", text),
+            None => String::new(),
+        }
+    }
+
+    fn token(&self, token: &Token) -> String {
+        format!("{}{}", self.attached(token.span.start), token.lexeme)
+    }
+
+    // `prefix` is written in front of the statement's first line, e.g. "} else ".
+    fn statement_with_prefix(&mut self, stmt: &Stmt, prefix: &str) {
+        match stmt {
+            Stmt::Block {
+                statements,
+                line,
+                end_line,
+                span,
+            } => {
+                let close = span.end - 1;
+                if self.is_empty_block(statements, close) {
+                    self.write_line(&format!("{}{{}}", prefix), *line);
+                    return;
+                }
+                self.write_line(&format!("{}{{", prefix), *line);
+                self.block_body(statements, close);
+                self.write_line("}", *end_line);
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                line,
+                ..
+            } => {
+                let header = format!("{}if ({})", prefix, self.expression(condition));
+                let else_prefix = self.body(&header, then_branch, *line, else_branch.is_some());
+
+                if let Some(else_branch) = else_branch {
+                    let prefix = format!("{}else {}", else_prefix, self.attached(else_branch.span().start));
+                    match else_branch.as_ref() {
+                        Stmt::If { .. } | Stmt::Block { .. } => {
+                            self.statement_with_prefix(else_branch, &prefix)
+                        }
+                        other => {
+                            self.body(prefix.trim_end(), other, other.line(), false);
+                        }
+                    }
+                }
+            }
+            Stmt::While {
+                condition,
+                body,
+                line,
+                ..
+            } => {
+                let header = format!("{}while ({})", prefix, self.expression(condition));
+                self.body(&header, body, *line, false);
+            }
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                line,
+                ..
+            } => {
+                let mut header = format!("{}for (", prefix);
+                match initializer {
+                    Some(init) => header.push_str(&self.simple_statement(init)),
+                    None => header.push(';'),
+                }
+                if let Some(condition) = condition {
+                    header.push(' ');
+                    header.push_str(&self.expression(condition));
+                }
+                header.push(';');
+                if let Some(increment) = increment {
+                    header.push(' ');
+                    header.push_str(&self.expression(increment));
+                }
+                header.push(')');
+                self.body(&header, body, *line, false);
+            }
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+                line,
+                ..
+            } => {
+                let header = format!("{}for ({} in {})", prefix, self.token(name), self.expression(iterable));
+                self.body(&header, body, *line, false);
+            }
+            Stmt::Function {
+                name,
+                params,
+                param_types,
+                return_type,
+                body,
+                end_line,
+                span,
+                ..
+            } => {
+                let params: Vec<String> = params
+                    .iter()
+                    .zip(param_types)
+                    .map(|(param, ty)| format!("{}{}", self.token(param), self.annotation(ty)))
+                    .collect();
+                let header = format!(
+                    "{}fun {}({}){} {{",
+                    prefix,
+                    self.token(name),
+                    params.join(", "),
+                    self.annotation(return_type)
+                );
+                let close = span.end - 1;
+                if self.is_empty_block(body, close) {
+                    self.write_line(&format!("{}}}", header), name.line);
+                    return;
+                }
+                self.write_line(&header, name.line);
+                self.block_body(body, close);
+                self.write_line("}", *end_line);
+            }
+            Stmt::Match {
+                subject,
+                arms,
+                line,
+                end_line,
+                span,
+            } => {
+                let header = format!("{}match ({}) {{", prefix, self.expression(subject));
+                let close = span.end - 1;
+                if arms.is_empty() && self.is_empty_block(&[], close) {
+                    self.write_line(&format!("{}}}", header), *line);
+                    return;
+                }
+                self.write_line(&header, *line);
+                self.indent += 1;
+                self.first_in_block = true;
+                for arm in arms {
+                    self.leading_comments(Self::pattern_start(&arm.pattern));
+                    self.blank_line_before(arm.line);
+                    let mut header = self.pattern(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        header.push_str(&format!(" if {}", self.expression(guard)));
+                    }
+                    header.push_str(" =>");
+                    // Short arms stay on the pattern's line.
+                    match &arm.body {
+                        Stmt::Expression { .. }
+                        | Stmt::Print { .. }
+                        | Stmt::Var { .. }
+                        | Stmt::Input { .. }
+                        | Stmt::Return { .. }
+                        | Stmt::Yield { .. } => {
+                            let text = format!("{} {}", header, self.simple_statement(&arm.body));
+                            self.write_line(&text, arm.line);
+                        }
+                        body => {
+                            self.body(&header, body, arm.line, false);
+                        }
+                    }
+                }
+                self.leading_comments(close);
+                self.indent -= 1;
+                self.first_in_block = false;
+                self.write_line("}", *end_line);
+            }
+            _ => {
+                let text = format!("{}{}", prefix, self.simple_statement(stmt));
+                self.write_line(&text, stmt.line());
+            }
+        }
+    }
+
+    // Writes a loop or branch header followed by its body. When `keep_open` is set and the
+    // body is a block, the closing brace is left for the caller and returned as a prefix.
+    fn body(&mut self, header: &str, body: &Stmt, line: usize, keep_open: bool) -> String {
+        match body {
+            Stmt::Block {
+                statements,
+                line: open_line,
+                end_line,
+                span,
+            } => {
+                let close = span.end - 1;
+                if self.is_empty_block(statements, close) {
+                    if keep_open {
+                        return format!("{} {{}} ", header);
+                    }
+                    self.write_line(&format!("{} {{}}", header), *open_line);
+                    return String::new();
+                }
+                self.write_line(&format!("{} {{", header), *open_line);
+                self.block_body(statements, close);
+                if keep_open {
+                    return "} ".to_string();
+                }
+                self.write_line("}", *end_line);
+            }
+            _ => {
+                self.write_line(header, line);
+                self.indent += 1;
+                self.first_in_block = true;
+                self.statement(body);
+                self.indent -= 1;
+            }
+        }
+        String::new()
+    }
+
+    // `close` is the byte offset of the block's `}`; comments before it stay inside.
+    fn block_body(&mut self, statements: &[Stmt], close: usize) {
+        self.indent += 1;
+        self.first_in_block = true;
+        for stmt in statements {
+            self.statement(stmt);
+        }
+        self.leading_comments(close);
+        self.indent -= 1;
+        self.first_in_block = false;
+    }
+
+    // An empty block with no comments inside prints as `{}`.
+    fn is_empty_block(&self, statements: &[Stmt], close: usize) -> bool {
+        statements.is_empty()
+            && self
+                .comments
+                .get(self.next_comment)
+                .is_none_or(|comment| comment.span.start >= close)
+    }
+
+    // Statements that always fit on one line.
+    fn simple_statement(&self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Expression { expression, .. } => format!("{};", self.expression(expression)),
+            Stmt::Print { expression, .. } => format!("write({});", self.expression(expression)),
+            Stmt::Var {
+                name,
+                type_annotation,
+                initializer,
+                ..
+            } => {
+                let ty = self.annotation(type_annotation);
+                match initializer {
+                    Some(init) => format!("var {}{} = {};", self.token(name), ty, self.expression(init)),
+                    None => format!("var {}{};", self.token(name), ty),
+                }
+            }
+            Stmt::Input { name, .. } => format!("scan({});", self.token(name)),
+            Stmt::Return { value, .. } => match value {
+                Some(value) => format!("return {};", self.expression(value)),
+                None => "return;".to_string(),
+            },
+            Stmt::Yield { value, .. } => match value {
+                Some(value) => format!("yield {};", self.expression(value)),
+                None => "yield;".to_string(),
+            },
+            _ => String::new(),
+        }
+    }
+
+    fn pattern(&self, pattern: &Pattern) -> String {
+        match pattern {
+            Pattern::Literal { value } => self.expression(value),
+            Pattern::Range { start, operator, end } => {
+                format!("{}{}{}", self.expression(start), self.token(operator), self.expression(end))
+            }
+            Pattern::Wildcard { token } => self.token(token),
+            Pattern::Binding { name } => self.token(name),
+        }
+    }
+
+    fn pattern_start(pattern: &Pattern) -> usize {
+        match pattern {
+            Pattern::Literal { value } => value.span().start,
+            Pattern::Range { start, .. } => start.span().start,
+            Pattern::Wildcard { token } => token.span.start,
+            Pattern::Binding { name } => name.span.start,
+        }
+    }
+
+    fn annotation(&self, type_name: &Option<Token>) -> String {
+        type_name
+            .as_ref()
+            .map_or(String::new(), |ty| format!(": {}", self.token(ty)))
+    }
+
+    // Writes every comment that starts before byte `offset` on its own line.
+    fn leading_comments(&mut self, offset: usize) {
+        self.stops.insert(offset);
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.span.start >= offset {
+                break;
+            }
+            let (text, comment_line) = (comment.text.clone(), comment.line);
+            self.next_comment += 1;
+            self.blank_line_before(comment_line);
+            self.write_raw_line(&text);
+        }
+    }
+
+    // Takes the trailing comment on `line`, if there is one.
+    fn trailing_comment(&mut self, line: usize) -> Option<String> {
+        let comment = self.comments.get(self.next_comment)?;
+        if comment.line == line && comment.trailing {
+            self.next_comment += 1;
+            return Some(self.comments[self.next_comment - 1].text.clone());
+        }
+        None
+    }
+
+    // Keeps a single blank line wherever the source had one before `line`.
+    fn blank_line_before(&mut self, line: usize) {
+        let blank_above = line >= 2
+            && self
+                .lines
+                .get(line - 2)
+                .is_some_and(|text| text.trim().is_empty());
+        if blank_above && !self.first_in_block {
+            self.output.push('\n');
+        }
+        self.first_in_block = false;
+    }
+
+    fn write_line(&mut self, text: &str, line: usize) {
+        match self.trailing_comment(line) {
+            Some(comment) => self.write_raw_line(&format!("{} {}", text, comment)),
+            None => self.write_raw_line(text),
+        }
+    }
+
+    fn write_raw_line(&mut self, text: &str) {
+        self.output.push_str(&INDENT.repeat(self.indent));
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+}
+
+impl ExprVisitor for Formatter<'_> {
+    fn visit_binary(&self, expr: &Binary) -> String {
+        format!(
+            "{} {} {}",
+            self.expression(&expr.left),
+            self.token(&expr.operator),
+            self.expression(&expr.right)
+        )
+    }
+
+    fn visit_grouping(&self, expr: &Grouping) -> String {
+        format!("({})", self.expression(&expr.expression))
+    }
+
+    fn visit_literal(&self, expr: &Literal) -> String {
+        let value = expr.value.downcast_ref::<TokenLiteral>();
+        // Numbers and strings are copied as written, so `0xFF`, `1_000`, escapes, raw and
+        // multi-line strings survive.
+        if let Some(
+            TokenLiteral::Integer(_)
+            | TokenLiteral::BigInteger(_)
+            | TokenLiteral::Number(_)
+            | TokenLiteral::String(_),
+        ) = value
+        {
+            if let Some(text) = self.source.get(expr.span.start..expr.span.end) {
+                if expr.span.end > expr.span.start {
+                    return text.to_string();
+                }
+            }
+        }
+        match value {
+            Some(TokenLiteral::Integer(n)) => n.to_string(),
+            Some(TokenLiteral::BigInteger(n)) => n.to_string(),
+            Some(TokenLiteral::Number(n)) => format_float(*n),
+            Some(TokenLiteral::String(s)) => format!("{:?}", s),
+            Some(TokenLiteral::Boolean(b)) => b.to_string(),
+            Some(TokenLiteral::Identifier(id)) => id.clone(),
+            Some(TokenLiteral::Null) | None => "nil".to_string(),
+        }
+    }
+
+    fn visit_unary(&self, expr: &Unary) -> String {
+        let right = self.expression(&expr.right);
+        if right.starts_with(&expr.operator.lexeme) {
+            return format!("{} {}", expr.operator.lexeme, right); // `- -x`, not `--x`
+        }
+        format!("{}{}", expr.operator.lexeme, right)
+    }
+
+    fn visit_variable(&self, expr: &Variable) -> String {
+        expr.name.lexeme.clone()
+    }
+
+    fn visit_assign(&self, name: &Token, value: &Expr) -> String {
+        format!("{} = {}", name.lexeme, self.expression(value))
+    }
+
+    fn visit_if(&self, condition: &Expr, then_branch: &Expr, else_branch: Option<&Expr>) -> String {
+        let mut text = format!("if ({}) {}", self.expression(condition), self.expression(then_branch));
+        if let Some(else_branch) = else_branch {
+            text.push_str(&format!(" else {}", self.expression(else_branch)));
+        }
+        text
+    }
+
+    fn visit_logical(&self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        format!("{} {} {}", self.expression(left), self.token(operator), self.expression(right))
+    }
+
+    fn visit_call(&self, callee: &Expr, arguments: &[Expr]) -> String {
+        let arguments: Vec<String> = arguments.iter().map(|arg| self.expression(arg)).collect();
+        format!("{}({})", self.expression(callee), arguments.join(", "))
+    }
+
+    fn visit_compound_assign(&self, name: &Token, operator: &Token, value: &Expr) -> String {
+        format!("{} {} {}", name.lexeme, self.token(operator), self.expression(value))
+    }
+
+    fn visit_increment(&self, name: &Token, operator: &Token, prefix: bool) -> String {
+        if prefix {
+            format!("{}{}", operator.lexeme, name.lexeme)
+        } else {
+            format!("{}{}", name.lexeme, operator.lexeme)
+        }
+    }
+
+    // The string parts span their `"`, `${` and `}`, so they join up around the expressions.
+    fn visit_interpolation(&self, parts: &[Expr]) -> String {
+        parts.iter().map(|part| self.expression(part)).collect()
+    }
+
+    fn visit_range(&self, start: &Expr, operator: &Token, end: &Expr, step: Option<&Expr>) -> String {
+        let range = format!("{}{}{}", self.expression(start), self.token(operator), self.expression(end));
+        match step {
+            Some(step) => format!("{} step {}", range, self.expression(step)),
+            None => range,
+        }
+    }
+}
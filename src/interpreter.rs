@@ -1,7 +1,10 @@
 use crate::environment::{self, Environment};
 use crate::expr::Expr;
-use crate::stmt::Stmt;
-use crate::token::{Token, TokenLiteral, TokenType};
+use crate::stmt::{MatchArm, Pattern, Stmt};
+use crate::token::{format_float, Token, TokenLiteral, TokenType};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
 use std::any::Any;
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -11,8 +14,9 @@ use std::sync::Arc;
 struct Function {
     name: String,
     params: Vec<Token>,
-    body: Vec<Box<Stmt>>,
+    body: Vec<Stmt>,
     closure: Rc<RefCell<Environment>>, // Captures the defining environment
+    generator: bool,                   // the body contains `yield`
 }
 
 // Implement Send and Sync for Function to satisfy Arc<dyn Any + Send + Sync>
@@ -23,48 +27,726 @@ impl Function {
     fn new(
         name: String,
         params: Vec<Token>,
-        body: Vec<Box<Stmt>>,
+        body: Vec<Stmt>,
         closure: Rc<RefCell<Environment>>,
     ) -> Self {
         Function {
             name,
             params,
+            generator: body.iter().any(Stmt::contains_yield),
             body,
             closure,
         }
     }
 }
+type Value = Arc<dyn Any + Send + Sync>;
+
+// Why statements stopped before their end: a runtime error, or a `return` carrying its
+// value back to the call.
+enum Unwind {
+    Error(String),
+    Return(Value),
+}
+
+impl From<String> for Unwind {
+    fn from(message: String) -> Self {
+        Unwind::Error(message)
+    }
+}
+
+/// An integer range from `a..b` or `a..=b`. The step is never zero.
+#[derive(Clone)]
+struct Range {
+    start: i64,
+    end: i64,
+    step: i64,
+    inclusive: bool,
+}
+
+impl Range {
+    // Counts from `start` towards `end`; a negative step counts down.
+    fn values(&self) -> impl Iterator<Item = i64> {
+        let Range { start, end, step, inclusive } = self.clone();
+        let mut next = Some(start);
+        std::iter::from_fn(move || {
+            let current = next?;
+            let within = match (step > 0, inclusive) {
+                (true, true) => current <= end,
+                (true, false) => current < end,
+                (false, true) => current >= end,
+                (false, false) => current > end,
+            };
+            if !within {
+                return None;
+            }
+            next = current.checked_add(step);
+            Some(current)
+        })
+    }
+}
+
+/// A call of a function containing `yield`, paused at its last `yield`. Copies of the
+/// value share one state, so advancing one advances them all.
+#[derive(Clone)]
+struct Generator {
+    name: String,
+    state: Rc<RefCell<GeneratorState>>,
+}
+
+// Same reasoning as for `Function`: values never leave the interpreter's thread.
+unsafe impl Send for Generator {}
+unsafe impl Sync for Generator {}
+
+struct GeneratorState {
+    environment: Rc<RefCell<Environment>>, // the scope the body stopped in
+    tasks: Vec<Task>,                      // what is left of the body, next last; empty once finished
+}
+
+// Part of a generator body still to run. Statements that contain a `yield` are taken apart
+// into these so the body can stop at the `yield` and carry on from there when resumed.
+enum Task {
+    Run(Box<Stmt>),
+    While {
+        condition: Box<Expr>,
+        body: Box<Stmt>,
+    },
+    For {
+        condition: Option<Box<Expr>>,
+        increment: Option<Box<Expr>>,
+        body: Box<Stmt>,
+        first: bool, // the increment doesn't run before the first pass
+    },
+    ForIn {
+        name: Box<Token>,
+        values: Values,
+        body: Box<Stmt>,
+    },
+    Leave(Rc<RefCell<Environment>>), // end of a scope: back to the enclosing environment
+}
+
+// What a `for (x in ...)` loop takes its values from.
+enum Values {
+    Items(Box<dyn Iterator<Item = Value>>),
+    Generator(Generator),
+}
+
+// Integers for a range, one-character strings for a string, whatever a generator yields.
+fn iterate(value: &Value) -> Result<Values, String> {
+    if let Some(range) = value.downcast_ref::<Range>() {
+        return Ok(Values::Items(Box::new(range.values().map(|n| Arc::new(n) as Value))));
+    }
+    if let Some(s) = value.downcast_ref::<String>() {
+        let chars: Vec<Value> = s.chars().map(|c| Arc::new(c.to_string()) as Value).collect();
+        return Ok(Values::Items(Box::new(chars.into_iter())));
+    }
+    if let Some(generator) = value.downcast_ref::<Generator>() {
+        return Ok(Values::Generator(generator.clone()));
+    }
+    Err(format!(
+        "Can only iterate over ranges, strings and generators, found {}.",
+        describe_value(value)
+    ))
+}
+
+/// A function implemented in Rust and predefined in the global scope.
+#[derive(Clone)]
+struct NativeFunction {
+    name: &'static str,
+    function: fn(&mut Interpreter, &[Value]) -> Result<Value, String>,
+}
+
+const NATIVES: [NativeFunction; 6] = [
+    NativeFunction {
+        name: "assert",
+        function: native_assert,
+    },
+    NativeFunction {
+        name: "assert_eq",
+        function: native_assert_eq,
+    },
+    NativeFunction {
+        name: "assert_ne",
+        function: native_assert_ne,
+    },
+    NativeFunction {
+        name: "str",
+        function: native_str,
+    },
+    NativeFunction {
+        name: "num",
+        function: native_num,
+    },
+    NativeFunction {
+        name: "next",
+        function: native_next,
+    },
+];
+
+/// Names of the predefined functions, for tools that offer completions.
+pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+    NATIVES.iter().map(|native| native.name)
+}
+
+// `assert(condition)` or `assert(condition, message)`.
+fn native_assert(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let message = assert_message("assert", args, 1)?;
+    match args[0].downcast_ref::<bool>() {
+        Some(true) => Ok(Arc::new(())),
+        Some(false) => Err(format!("Assertion failed{}.", message)),
+        None => Err(format!("assert expects a boolean condition, found {}.", describe_value(&args[0]))),
+    }
+}
+
+// `assert_eq(actual, expected)` or `assert_eq(actual, expected, message)`.
+fn native_assert_eq(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let message = assert_message("assert_eq", args, 2)?;
+    if values_equal(&args[0], &args[1]) {
+        return Ok(Arc::new(()));
+    }
+    let mut report = format!(
+        "assert_eq failed{}\n  left:  {}\n  right: {}",
+        message,
+        describe_value(&args[0]),
+        describe_value(&args[1])
+    );
+    if let (Some(left), Some(right)) = (args[0].downcast_ref::<String>(), args[1].downcast_ref::<String>()) {
+        if left.contains('\n') || right.contains('\n') {
+            report.push_str("\n  diff (-left +right):");
+            report.push_str(&line_diff(left, right));
+        }
+    }
+    Err(report)
+}
+
+// `assert_ne(left, right)` or `assert_ne(left, right, message)`.
+fn native_assert_ne(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let message = assert_message("assert_ne", args, 2)?;
+    if values_equal(&args[0], &args[1]) {
+        return Err(format!("assert_ne failed{}\n  both: {}", message, describe_value(&args[0])));
+    }
+    Ok(Arc::new(()))
+}
+
+// `str(value)`: the text `write` would print, for any value.
+fn native_str(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [value] = args else {
+        return Err(format!("str expects 1 argument, found {}.", args.len()));
+    };
+    Ok(Arc::new(display_value(value)))
+}
+
+// `num(value)`: a number, or the number a string spells (`"42"`, `"-1.5"`, or digits
+// too many for 64 bits).
+fn native_num(_interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [value] = args else {
+        return Err(format!("num expects 1 argument, found {}.", args.len()));
+    };
+    if value.is::<i64>() || value.is::<BigInt>() || value.is::<f64>() {
+        return Ok(value.clone());
+    }
+    let Some(text) = value.downcast_ref::<String>() else {
+        return Err(format!("num expects a number or a string, found {}.", describe_value(value)));
+    };
+    parse_number(text.trim()).ok_or_else(|| format!("Cannot convert {:?} to a number.", text))
+}
+
+// `next(generator)`: runs the generator to its next `yield` and gives the yielded value,
+// or nil once the generator has finished.
+fn native_next(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let [value] = args else {
+        return Err(format!("next expects 1 argument, found {}.", args.len()));
+    };
+    let Some(generator) = value.downcast_ref::<Generator>() else {
+        return Err(format!("next expects a generator, found {}.", describe_value(value)));
+    };
+    Ok(interpreter.resume(generator)?.unwrap_or_else(|| Arc::new(())))
+}
+
+// An integer if `text` is one (big if it must be), otherwise a float.
+fn parse_number(text: &str) -> Option<Value> {
+    if let Ok(n) = text.parse::<i64>() {
+        return Some(Arc::new(n));
+    }
+    if let Ok(n) = text.parse::<BigInt>() {
+        return Some(Arc::new(n));
+    }
+    text.parse::<f64>().ok().map(|n| Arc::new(n) as Value)
+}
+
+// Checks the argument count and renders the optional trailing message as `: message`.
+fn assert_message(name: &str, args: &[Value], required: usize) -> Result<String, String> {
+    if args.len() != required && args.len() != required + 1 {
+        return Err(format!(
+            "{} expects {} or {} arguments, found {}.",
+            name,
+            required,
+            required + 1,
+            args.len()
+        ));
+    }
+    Ok(match args.get(required) {
+        Some(message) => match message.downcast_ref::<String>() {
+            Some(text) => format!(": {}", text),
+            None => format!(": {}", describe_value(message)),
+        },
+        None => String::new(),
+    })
+}
+
+// The operands of an arithmetic operator. Two integers stay integers, big ones once either
+// is too large for 64 bits; as soon as either side is a float, both are.
+enum Numbers {
+    Int(i64, i64),
+    Big(BigInt, BigInt),
+    Float(f64, f64),
+}
+
+fn numbers(left: &Value, right: &Value) -> Option<Numbers> {
+    if let (Some(l), Some(r)) = (left.downcast_ref::<i64>(), right.downcast_ref::<i64>()) {
+        return Some(Numbers::Int(*l, *r));
+    }
+    if let (Some(l), Some(r)) = (big_integer(left), big_integer(right)) {
+        return Some(Numbers::Big(l, r));
+    }
+    Some(Numbers::Float(float(left)?, float(right)?))
+}
+
+// How two numbers order. None if either is not a number, or one is NaN.
+fn compare(left: &Value, right: &Value) -> Option<std::cmp::Ordering> {
+    match numbers(left, right)? {
+        Numbers::Int(l, r) => l.partial_cmp(&r),
+        Numbers::Big(l, r) => l.partial_cmp(&r),
+        Numbers::Float(l, r) => l.partial_cmp(&r),
+    }
+}
+
+fn big_integer(value: &Value) -> Option<BigInt> {
+    if let Some(n) = value.downcast_ref::<i64>() {
+        return Some(BigInt::from(*n));
+    }
+    value.downcast_ref::<BigInt>().cloned()
+}
+
+fn float(value: &Value) -> Option<f64> {
+    if let Some(n) = value.downcast_ref::<BigInt>() {
+        return n.to_f64();
+    }
+    value
+        .downcast_ref::<f64>()
+        .copied()
+        .or_else(|| value.downcast_ref::<i64>().map(|n| *n as f64))
+}
+
+// A number with no fractional part as i64, for the bitwise operators.
+fn integer(value: &Value) -> Option<i64> {
+    if let Some(n) = value.downcast_ref::<i64>() {
+        return Some(*n);
+    }
+    float_to_integer(*value.downcast_ref::<f64>()?)
+}
+
+// Any whole number as a big integer, for bitwise operators on values past 64 bits.
+fn whole(value: &Value) -> Option<BigInt> {
+    if let Some(n) = value.downcast_ref::<f64>() {
+        return if n.fract() == 0.0 { BigInt::from_f64(*n) } else { None };
+    }
+    big_integer(value)
+}
+
+fn float_to_integer(n: f64) -> Option<i64> {
+    // i64::MAX as f64 rounds up to 2^63, which is already out of range.
+    (n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64).then_some(n as i64)
+}
+
+// Big integers are capped so a runaway `x = x * x` fails instead of eating all memory.
+const MAX_INTEGER_BITS: u64 = 1 << 20;
+
+// An integer result: i64 when it fits, so each value has exactly one representation.
+fn big(n: BigInt) -> Result<Value, String> {
+    if let Some(small) = n.to_i64() {
+        return Ok(Arc::new(small));
+    }
+    if n.bits() > MAX_INTEGER_BITS {
+        return Err("Integer too large.".to_string());
+    }
+    Ok(Arc::new(n))
+}
+
+// The result of i64 arithmetic, or of the same operation on big integers if it overflowed.
+fn promote(small: Option<i64>, l: i64, r: i64, op: fn(BigInt, BigInt) -> BigInt) -> Result<Value, String> {
+    match small {
+        Some(n) => Ok(Arc::new(n)),
+        None => big(op(BigInt::from(l), BigInt::from(r))),
+    }
+}
+
+// Strings are capped the same way, so a runaway `s = s + s` fails too.
+const MAX_STRING_BYTES: usize = 1 << 20;
+
+fn text(text: String) -> Result<Value, String> {
+    if text.len() > MAX_STRING_BYTES {
+        return Err("String too large.".to_string());
+    }
+    Ok(Arc::new(text))
+}
+
+// `base ** exponent` for a non-negative exponent, checking the size before computing it.
+fn big_pow(base: BigInt, exponent: BigInt) -> Result<Value, String> {
+    // 0, 1 and -1 stay small whatever the exponent.
+    if base.abs() <= BigInt::from(1) {
+        if exponent.is_zero() || (base.is_negative() && exponent.is_even()) {
+            return big(BigInt::from(1));
+        }
+        return big(base);
+    }
+    match exponent.to_u64() {
+        Some(exponent) if base.bits().saturating_mul(exponent) <= MAX_INTEGER_BITS => {
+            big(base.pow(exponent as u32))
+        }
+        _ => Err("Integer too large.".to_string()),
+    }
+}
+
+// `l << amount` or `l >> amount`. `<<` grows the integer instead of dropping bits, and `>>`
+// keeps the sign, rounding down.
+fn big_shift(l: BigInt, amount: BigInt, left: bool) -> Result<Value, String> {
+    if amount.is_negative() {
+        return Err("Shift amount must not be negative.".to_string());
+    }
+    if l.is_zero() {
+        return big(l);
+    }
+    let amount = amount.to_u64().unwrap_or(u64::MAX);
+    if !left {
+        // Past the top bit only the sign is left: 0 or -1.
+        let amount = amount.min(l.bits());
+        return big(l >> amount);
+    }
+    if l.bits().saturating_add(amount) > MAX_INTEGER_BITS {
+        return Err("Integer too large.".to_string());
+    }
+    big(l << amount)
+}
+
+// `l / r` as a float, scaling both down first so huge operands don't both become infinity.
+fn big_ratio(l: &BigInt, r: &BigInt) -> f64 {
+    let shift = l.bits().max(r.bits()).saturating_sub(1000);
+    let (l, r) = (l >> shift, r >> shift);
+    l.to_f64().unwrap_or(f64::NAN) / r.to_f64().unwrap_or(f64::NAN)
+}
+
+// The integer a float `~/` rounds to.
+fn float_quotient(quotient: f64) -> Result<Value, String> {
+    if let Some(n) = float_to_integer(quotient) {
+        return Ok(Arc::new(n));
+    }
+    match BigInt::from_f64(quotient) {
+        Some(n) => big(n),
+        None => Err("Quotient is not a finite number.".to_string()),
+    }
+}
+
+// Equality as `assert_eq` sees it: `1 == 1.0`, as for `==`.
+fn values_equal(left: &Value, right: &Value) -> bool {
+    let is_nil = |value: &Value| value.is::<()>() || value.is::<TokenLiteral>();
+    match numbers(left, right) {
+        Some(Numbers::Int(l, r)) => return l == r,
+        Some(Numbers::Big(l, r)) => return l == r,
+        Some(Numbers::Float(l, r)) => return l == r,
+        None => {}
+    }
+    if let (Some(l), Some(r)) = (left.downcast_ref::<String>(), right.downcast_ref::<String>()) {
+        return l == r;
+    }
+    if let (Some(l), Some(r)) = (left.downcast_ref::<bool>(), right.downcast_ref::<bool>()) {
+        return l == r;
+    }
+    is_nil(left) && is_nil(right)
+}
+
+// Line-by-line comparison of two multi-line strings, one `-`/`+` pair per differing line.
+fn line_diff(left: &str, right: &str) -> String {
+    let left: Vec<&str> = left.lines().collect();
+    let right: Vec<&str> = right.lines().collect();
+    let mut out = String::new();
+    for index in 0..left.len().max(right.len()) {
+        match (left.get(index), right.get(index)) {
+            (Some(l), Some(r)) if l == r => out.push_str(&format!("\n     {}", l)),
+            (l, r) => {
+                if let Some(l) = l {
+                    out.push_str(&format!("\n    -{}", l));
+                }
+                if let Some(r) = r {
+                    out.push_str(&format!("\n    +{}", r));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// The text of a value as `write`, `str` and string interpolation produce it: strings as
+/// they are, everything else as [`describe_value`] renders it.
+pub fn display_value(value: &Arc<dyn Any + Send + Sync>) -> String {
+    match value.downcast_ref::<String>() {
+        Some(text) => text.clone(),
+        None => describe_value(value),
+    }
+}
+
+/// Renders any runtime value for tools (debugger views and the like), with strings quoted.
+pub fn describe_value(value: &Arc<dyn Any + Send + Sync>) -> String {
+    if let Some(v) = value.downcast_ref::<bool>() {
+        v.to_string()
+    } else if let Some(function) = value.downcast_ref::<Function>() {
+        format!("<fn {}>", function.name)
+    } else if let Some(native) = value.downcast_ref::<NativeFunction>() {
+        format!("<native fn {}>", native.name)
+    } else if let Some(generator) = value.downcast_ref::<Generator>() {
+        format!("<generator {}>", generator.name)
+    } else if let Some(s) = value.downcast_ref::<String>() {
+        format!("{:?}", s)
+    } else if value.is::<()>() || value.is::<TokenLiteral>() {
+        "nil".to_string()
+    } else if let Some(v) = value.downcast_ref::<i64>() {
+        v.to_string()
+    } else if let Some(v) = value.downcast_ref::<BigInt>() {
+        v.to_string()
+    } else if let Some(v) = value.downcast_ref::<f64>() {
+        format_float(*v)
+    } else if let Some(range) = value.downcast_ref::<Range>() {
+        let operator = if range.inclusive { "..=" } else { ".." };
+        let mut text = format!("{}{}{}", range.start, operator, range.end);
+        if range.step != 1 {
+            text.push_str(&format!(" step {}", range.step));
+        }
+        text
+    } else {
+        "(Unknown type)".to_string()
+    }
+}
+
+/// Callbacks made while a program runs, for tools such as the debugger.
+pub trait Hook {
+    /// Called before each statement other than a block. Returning an error stops the program.
+    fn statement(&mut self, _stmt: &Stmt, _frames: &[Frame]) -> Result<(), String> {
+        Ok(())
+    }
+    /// Called once the new frame is on top of `frames`.
+    fn enter_function(&mut self, _frames: &[Frame]) {}
+    /// Called while the returning frame is still on top of `frames`.
+    fn exit_function(&mut self, _frames: &[Frame]) {}
+    /// Called with everything the program writes, runtime errors included.
+    fn output(&mut self, _text: &str) {}
+}
+
+/// One entry of the call stack. The bottom frame is the script itself.
+pub struct Frame {
+    pub function: String,
+    pub line: usize, // line of the statement being executed
+    pub environment: Rc<RefCell<Environment>>,
+    statement_lines: Vec<usize>, // lines of the statements executing in this frame, innermost last
+}
+
+impl Frame {
+    fn new(function: &str, environment: Rc<RefCell<Environment>>) -> Self {
+        Frame {
+            function: function.to_string(),
+            line: 0,
+            environment,
+            statement_lines: Vec::new(),
+        }
+    }
+
+    /// False when the current statement is nested in one that started on the same line,
+    /// such as the body of `if (x) write(x);`.
+    pub fn is_new_line(&self) -> bool {
+        match self.statement_lines.as_slice() {
+            [.., outer, inner] => outer != inner,
+            _ => true,
+        }
+    }
+}
+
 pub struct Interpreter {
     environment: Rc<RefCell<Environment>>,
     output: String,
+    frames: Vec<Frame>,
+    hook: Option<Rc<RefCell<dyn Hook>>>,
+    halted: bool,                // set when the hook stops the program
+    steps_left: Option<usize>,   // statements still allowed to run, see `set_step_limit`
+    output_limit: Option<usize>, // bytes the program may write, see `set_output_limit`
+    depth: usize,                // nested statements, expressions and calls being run
+}
+
+// Calls nested deeper than this fail with "Stack overflow." instead of overflowing the
+// interpreter's own stack.
+const MAX_CALL_DEPTH: usize = 200;
+
+// The parser caps nesting within one function, but calls stack those levels on top of each
+// other. Every statement, expression and call being run counts against this one budget, so
+// deep code inside deep recursion fails with "Stack overflow." too. A level takes up to about
+// 7KB of stack in a debug build, which keeps the total well inside the 8MB main thread.
+const MAX_DEPTH: usize = 800;
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
+
 impl Interpreter {
     pub fn new() -> Self {
+        let environment = Rc::new(RefCell::new(environment::Environment::new(None)));
+        for native in NATIVES {
+            environment
+                .borrow_mut()
+                .define(native.name.to_string(), Arc::new(native));
+        }
         Interpreter {
-            environment: Rc::new(RefCell::new(environment::Environment::new(None))),
+            environment: environment.clone(),
             output: String::new(),
+            frames: vec![Frame::new("<script>", environment)],
+            hook: None,
+            halted: false,
+            steps_left: None,
+            output_limit: None,
+            depth: 0,
         }
     }
 
+    pub fn set_hook(&mut self, hook: Rc<RefCell<dyn Hook>>) {
+        self.hook = Some(hook);
+    }
 
+    /// Stops the program with a runtime error once `limit` statements (loop iterations
+    /// included) have run, so untrusted programs always finish.
+    pub fn set_step_limit(&mut self, limit: usize) {
+        self.steps_left = Some(limit);
+    }
+
+    /// Stops the program with a runtime error once it has written more than `limit` bytes.
+    /// With a step limit alone, a loop writing a long string can still use up all memory.
+    pub fn set_output_limit(&mut self, limit: usize) {
+        self.output_limit = Some(limit);
+    }
 
     pub fn interpret(&mut self, statements: &[Stmt]) -> String {
         for statement in statements {
-            if let Err(err) = self.visit_stmt(statement) {
-                self.output.push_str(&format!("Runtime error: {}\n", err));
+            if let Err(unwind) = self.execute(statement) {
+                let err = match unwind {
+                    Unwind::Error(err) => err,
+                    Unwind::Return(_) => "Can't return from top-level code.".to_string(),
+                };
+                if self.halted {
+                    break;
+                }
+                self.emit(&format!("Runtime error: {}\n", err));
+                if self.steps_left == Some(0) {
+                    break;
+                }
             }
         }
         self.output.clone()
     }
-    
-    
-    
-    fn execute(&mut self, stmt: &Stmt) -> Result<(), String> {
-        self.visit_stmt(stmt)
+
+    /// Everything written so far.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Calls the global function `name` with no arguments, e.g. a test case. Generators are
+    /// refused: calling one only creates it, so none of its body would run.
+    pub fn call_global(&mut self, name: &str) -> Result<(), String> {
+        let token = Token::new(
+            TokenType::IDENTIFIER,
+            name.to_string(),
+            TokenLiteral::Identifier(name.to_string()),
+            0,
+        );
+        let value = self.environment.borrow().get(&token).map_err(|e| e.to_string())?;
+        let Some(function) = value.downcast_ref::<Function>() else {
+            return Err(format!("'{}' is not a function.", name));
+        };
+        if function.generator {
+            return Err(format!("'{}' is a generator and would never run; remove its 'yield'.", name));
+        }
+        self.call_function(function, Vec::new()).map(|_| ())
+    }
+
+    fn emit(&mut self, text: &str) {
+        self.output.push_str(text);
+        if let Some(hook) = &self.hook {
+            hook.borrow_mut().output(text);
+        }
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        self.descend()?;
+        let result = self.execute_stmt(stmt);
+        self.depth -= 1;
+        result
+    }
+
+    fn execute_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        self.count_step()?;
+        if matches!(stmt, Stmt::Block { .. }) {
+            return self.visit_stmt(stmt);
+        }
+        let frame = self.frames.last_mut().expect("the script frame is never popped");
+        frame.line = stmt.line();
+        frame.environment = self.environment.clone();
+        frame.statement_lines.push(stmt.line());
+
+        let result = match self.notify_statement(stmt) {
+            Ok(()) => self.visit_stmt(stmt),
+            Err(err) => Err(err.into()),
+        };
+
+        if let Some(frame) = self.frames.last_mut() {
+            frame.statement_lines.pop();
+        }
+        result
+    }
+
+    // Goes one level deeper into the program, see MAX_DEPTH; the caller steps back out.
+    fn descend(&mut self) -> Result<(), String> {
+        if self.depth >= MAX_DEPTH {
+            return Err("Stack overflow.".to_string());
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn count_step(&mut self) -> Result<(), String> {
+        if self.output_limit.is_some_and(|limit| self.output.len() > limit) {
+            return Err("Output limit exceeded.".to_string());
+        }
+        if let Some(steps) = &mut self.steps_left {
+            if *steps == 0 {
+                return Err("Step limit exceeded.".to_string());
+            }
+            *steps -= 1;
+        }
+        Ok(())
+    }
+
+    fn notify_statement(&mut self, stmt: &Stmt) -> Result<(), String> {
+        let Some(hook) = &self.hook else {
+            return Ok(());
+        };
+        let result = hook.borrow_mut().statement(stmt, &self.frames);
+        if result.is_err() {
+            self.halted = true;
+        }
+        result
     }
     fn is_truthy(&self, value: &Arc<dyn Any + Send + Sync>) -> bool {
         if let Some(b) = value.downcast_ref::<bool>() {
             *b
+        } else if let Some(n) = value.downcast_ref::<i64>() {
+            *n != 0
+        } else if value.is::<BigInt>() {
+            true // never zero, or it would be an i64
         } else if let Some(n) = value.downcast_ref::<f64>() {
             *n != 0.0
         } else if let Some(s) = value.downcast_ref::<String>() {
@@ -74,11 +756,11 @@ impl Interpreter {
         }
     }
 
-    pub fn execute_block(
+    fn execute_block(
         &mut self,
         statements: &[Stmt],
         environment: Rc<RefCell<Environment>>,
-    ) -> Result<(), String> {
+    ) -> Result<(), Unwind> {
         let previous = self.environment.clone(); //  Save old environment
         self.environment = environment.clone();
 
@@ -94,96 +776,341 @@ impl Interpreter {
         function: &Function,
         arguments: Vec<Arc<dyn Any + Send + Sync>>,
     ) -> Result<Arc<dyn Any + Send + Sync>, String> {
+        if self.frames.len() > MAX_CALL_DEPTH {
+            return Err("Stack overflow.".to_string());
+        }
         let environment = Rc::new(RefCell::new(Environment::new(Some(function.closure.clone()))));
     
         // Bind function parameters to arguments
         for (param, arg) in function.params.iter().zip(arguments.iter()) {
             environment.borrow_mut().define(param.lexeme.clone(), arg.clone());
         }
+
+        // A generator's body doesn't start until the first `next`
+        if function.generator {
+            let tasks = function.body.iter().rev().map(|stmt| Task::Run(Box::new(stmt.clone()))).collect();
+            return Ok(Arc::new(Generator {
+                name: function.name.clone(),
+                state: Rc::new(RefCell::new(GeneratorState { environment, tasks })),
+            }));
+        }
     
         // Store previous environment and switch to function's environment
         let previous_environment = self.environment.clone();
         self.environment = environment.clone();
+        self.frames.push(Frame::new(&function.name, environment.clone()));
+        if let Some(hook) = &self.hook {
+            hook.borrow_mut().enter_function(&self.frames);
+        }
     
-        let mut return_value: Option<Arc<dyn Any + Send + Sync>> = None;
-    
+        // Without a `return`, a call gives nil.
+        let mut result: Result<Value, String> = Ok(Arc::new(()));
         for stmt in &function.body {
-            match self.execute(&**stmt) {
-                Err(e) if e.starts_with("Return:") => {
-                    // Extract the value from the error message
-                    let value_str = e.strip_prefix("Return: ").unwrap_or("");
-    
-                    // Try to convert common types
-                    if value_str == "true" {
-                        return_value = Some(Arc::new(true));
-                    } else if value_str == "false" {
-                        return_value = Some(Arc::new(false));
-                    } else if let Ok(num) = value_str.parse::<i64>() {
-                        return_value = Some(Arc::new(num));
-                    } else if let Ok(num) = value_str.parse::<f64>() {
-                        return_value = Some(Arc::new(num));
-                    } else {
-                        // Prevent issues with large numbers or incorrect values
-                        // Checking for extremely large values and handling them appropriately
-                        if value_str.len() > 100 {
-                            return Err("Value too large to process.".to_string());
-                        }
-    
-                        // Remove the debug formatting artifacts from the string
-                        let cleaned_str = value_str.trim()
-                            .trim_start_matches("Some(")
-                            .trim_end_matches(")")
-                            .trim_matches('"');
-                        return_value = Some(Arc::new(cleaned_str.to_string()));
-                    }
+            match self.execute(stmt) {
+                Ok(()) => {}
+                Err(Unwind::Return(value)) => {
+                    result = Ok(value);
                     break;
                 }
-                Err(e) => {
-                    self.environment = previous_environment;
-                    return Err(e);
+                Err(Unwind::Error(e)) => {
+                    result = Err(e);
+                    break;
                 }
-                Ok(_) => continue,
             }
         }
-    
+
         // Restore previous environment
+        self.leave_function();
         self.environment = previous_environment;
-    
-        Ok(return_value.unwrap_or_else(|| Arc::new(0_i64)))
+        result
     }
     
 
-    fn visit_stmt(&mut self, stmt: &Stmt) -> Result<(), String> {
+    // Runs a generator until its next `yield`. None once the body has finished, and from
+    // then on.
+    fn resume(&mut self, generator: &Generator) -> Result<Option<Value>, String> {
+        let Ok(mut state) = generator.state.try_borrow_mut() else {
+            return Err(format!("Generator '{}' is already running.", generator.name));
+        };
+        if state.tasks.is_empty() {
+            return Ok(None);
+        }
+        if self.frames.len() > MAX_CALL_DEPTH {
+            return Err("Stack overflow.".to_string());
+        }
+        let previous = std::mem::replace(&mut self.environment, state.environment.clone());
+        self.frames.push(Frame::new(&generator.name, self.environment.clone()));
+        if let Some(hook) = &self.hook {
+            hook.borrow_mut().enter_function(&self.frames);
+        }
+
+        let result = self.run_tasks(&mut state.tasks);
+
+        self.leave_function();
+        state.environment = std::mem::replace(&mut self.environment, previous);
+        match result {
+            Ok(Some(value)) => Ok(Some(value)),
+            // `return` ends a generator; any value it gives is dropped
+            Err(Unwind::Return(_)) | Ok(None) => {
+                state.tasks.clear();
+                Ok(None)
+            }
+            Err(Unwind::Error(e)) => {
+                state.tasks.clear();
+                Err(e)
+            }
+        }
+    }
+
+    fn run_tasks(&mut self, tasks: &mut Vec<Task>) -> Result<Option<Value>, Unwind> {
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Run(stmt) => {
+                    if let Some(value) = self.start(stmt, tasks)? {
+                        return Ok(Some(value));
+                    }
+                }
+                Task::While { condition, body } => {
+                    let result = self.evaluate(&condition)?;
+                    if self.is_truthy(&result) {
+                        tasks.push(Task::While {
+                            condition,
+                            body: body.clone(),
+                        });
+                        tasks.push(Task::Run(body));
+                    }
+                }
+                Task::For {
+                    condition,
+                    increment,
+                    body,
+                    first,
+                } => {
+                    if let (false, Some(increment)) = (first, &increment) {
+                        self.evaluate(increment)?;
+                    }
+                    let again = match &condition {
+                        Some(condition) => {
+                            let result = self.evaluate(condition)?;
+                            self.is_truthy(&result)
+                        }
+                        None => true,
+                    };
+                    if again {
+                        tasks.push(Task::For {
+                            condition,
+                            increment,
+                            body: body.clone(),
+                            first: false,
+                        });
+                        tasks.push(Task::Run(body));
+                    }
+                }
+                Task::ForIn {
+                    name,
+                    mut values,
+                    body,
+                } => {
+                    if let Some(value) = self.next_value(&mut values)? {
+                        let mut scope = Environment::new(Some(self.environment.clone()));
+                        scope.define(name.lexeme.clone(), value);
+                        tasks.push(Task::ForIn {
+                            name,
+                            values,
+                            body: body.clone(),
+                        });
+                        tasks.push(Task::Leave(self.environment.clone()));
+                        tasks.push(Task::Run(body));
+                        self.environment = Rc::new(RefCell::new(scope));
+                    }
+                }
+                Task::Leave(environment) => self.environment = environment,
+            }
+        }
+        Ok(None)
+    }
+
+    // Starts a statement of a generator body. One without a `yield` runs to the end like
+    // anywhere else; the others push the tasks that make up the rest of them. Some(value)
+    // when the statement is the `yield` itself.
+    fn start(&mut self, stmt: Box<Stmt>, tasks: &mut Vec<Task>) -> Result<Option<Value>, Unwind> {
+        if !stmt.contains_yield() {
+            self.execute(&stmt)?;
+            return Ok(None);
+        }
+        self.count_step()?;
+        if !matches!(*stmt, Stmt::Block { .. }) {
+            let frame = self.frames.last_mut().expect("the script frame is never popped");
+            frame.line = stmt.line();
+            frame.environment = self.environment.clone();
+            self.notify_statement(&stmt)?;
+        }
+        match *stmt {
+            Stmt::Yield { value, .. } => {
+                let value = match value {
+                    Some(expr) => self.evaluate(&expr)?,
+                    None => Arc::new(()),
+                };
+                return Ok(Some(value));
+            }
+            Stmt::Block { statements, .. } => {
+                tasks.push(Task::Leave(self.environment.clone()));
+                self.environment = Rc::new(RefCell::new(Environment::new(Some(self.environment.clone()))));
+                tasks.extend(statements.into_iter().rev().map(|stmt| Task::Run(Box::new(stmt))));
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                let condition = self.evaluate(&condition)?;
+                match condition.downcast_ref::<bool>() {
+                    Some(true) => tasks.push(Task::Run(then_branch)),
+                    Some(false) => tasks.extend(else_branch.map(Task::Run)),
+                    None => return Err("Condition must be a boolean.".to_string().into()),
+                }
+            }
+            Stmt::While { condition, body, .. } => tasks.push(Task::While {
+                condition: Box::new(condition),
+                body,
+            }),
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                tasks.push(Task::Leave(self.environment.clone()));
+                self.environment = Rc::new(RefCell::new(Environment::new(Some(self.environment.clone()))));
+                if let Some(initializer) = initializer {
+                    self.execute(&initializer)?;
+                }
+                tasks.push(Task::For {
+                    condition: condition.map(Box::new),
+                    increment: increment.map(Box::new),
+                    body,
+                    first: true,
+                });
+            }
+            Stmt::Match { subject, arms, .. } => {
+                let value = self.evaluate(&subject)?;
+                let (arm, scope) = self.choose_arm(&value, &arms)?;
+                tasks.push(Task::Leave(self.environment.clone()));
+                tasks.push(Task::Run(Box::new(arm.body.clone())));
+                self.environment = scope;
+            }
+            Stmt::ForIn {
+                name, iterable, body, ..
+            } => {
+                let iterable = self.evaluate(&iterable)?;
+                tasks.push(Task::ForIn {
+                    name: Box::new(name),
+                    values: iterate(&iterable)?,
+                    body,
+                });
+            }
+            _ => unreachable!("only the statements above contain a yield"),
+        }
+        Ok(None)
+    }
+
+    // The first arm whose pattern and guard accept `value`, and the scope its body runs in.
+    fn choose_arm<'a>(
+        &mut self,
+        value: &Value,
+        arms: &'a [MatchArm],
+    ) -> Result<(&'a MatchArm, Rc<RefCell<Environment>>), String> {
+        for arm in arms {
+            if !self.pattern_matches(&arm.pattern, value)? {
+                continue;
+            }
+            let scope = Rc::new(RefCell::new(Environment::new(Some(self.environment.clone()))));
+            if let Pattern::Binding { name } = &arm.pattern {
+                scope.borrow_mut().define(name.lexeme.clone(), value.clone());
+            }
+            if let Some(guard) = &arm.guard {
+                let previous = std::mem::replace(&mut self.environment, scope.clone());
+                let result = self.evaluate(guard);
+                self.environment = previous;
+                match result?.downcast_ref::<bool>() {
+                    Some(true) => {}
+                    Some(false) => continue,
+                    None => return Err("Match guard must be a boolean.".to_string()),
+                }
+            }
+            return Ok((arm, scope));
+        }
+        Err(format!("No match arm for {}.", describe_value(value)))
+    }
+
+    fn pattern_matches(&mut self, pattern: &Pattern, value: &Value) -> Result<bool, String> {
+        match pattern {
+            Pattern::Literal { value: expected } => {
+                let expected = self.evaluate(expected)?;
+                Ok(values_equal(value, &expected))
+            }
+            Pattern::Range { start, operator, end } => {
+                let low = self.evaluate(start)?;
+                let high = self.evaluate(end)?;
+                if float(&low).is_none() || float(&high).is_none() {
+                    return Err("Range pattern bounds must be numbers.".to_string());
+                }
+                // Anything but a number is simply outside the range.
+                let (Some(from_low), Some(to_high)) = (compare(value, &low), compare(value, &high)) else {
+                    return Ok(false);
+                };
+                let below_end = match operator.token_type {
+                    TokenType::DOT_DOT_EQUAL => to_high.is_le(),
+                    _ => to_high.is_lt(),
+                };
+                Ok(from_low.is_ge() && below_end)
+            }
+            Pattern::Wildcard { .. } | Pattern::Binding { .. } => Ok(true),
+        }
+    }
+
+    fn next_value(&mut self, values: &mut Values) -> Result<Option<Value>, String> {
+        match values {
+            Values::Items(items) => Ok(items.next()),
+            Values::Generator(generator) => self.resume(generator),
+        }
+    }
+
+    fn leave_function(&mut self) {
+        if let Some(hook) = &self.hook {
+            hook.borrow_mut().exit_function(&self.frames);
+        }
+        self.frames.pop();
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
         match stmt {
+            Stmt::Match { subject, arms, .. } => {
+                let value = self.evaluate(subject)?;
+                let (arm, scope) = self.choose_arm(&value, arms)?;
+                let previous = std::mem::replace(&mut self.environment, scope);
+                let result = self.execute(&arm.body);
+                self.environment = previous;
+                result
+            }
+            // Inside a function, a `yield` makes it a generator and runs from `start` instead.
+            Stmt::Yield { .. } => Err("Can't yield outside a function.".to_string().into()),
             Stmt::Return { value, .. } => {
-                let value = if let Some(expr) = value {
-                    self.evaluate(expr)?
-                } else {
-                    Arc::new(())
+                let value = match value {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => Arc::new(()),
                 };
-                
-                // Create a cleaner string representation for the return value
-                let return_str = if let Some(v) = value.downcast_ref::<i64>() {
-                    format!("Return: {}", v)
-                } else if let Some(v) = value.downcast_ref::<f64>() {
-                    format!("Return: {}", v)
-                } else if let Some(v) = value.downcast_ref::<bool>() {
-                    format!("Return: {}", v)
-                } else if let Some(v) = value.downcast_ref::<String>() {
-                    format!("Return: {}", v)
-                } else if value.is::<()>() {
-                    String::from("Return: nil")
-                } else {
-                    format!("Return: {}", self.stringify(&value))
-                };
-                
-                Err(return_str)
+                Err(Unwind::Return(value))
             }
-            Stmt::Function { name, params, body } => {
+            Stmt::Function {
+                name, params, body, ..
+            } => {
                 let function = Arc::new(Function::new(
                     name.lexeme.clone(),
                     params.clone(),
-                    body.iter().map(|stmt| Box::new(stmt.clone())).collect(),
+                    body.clone(),
                     self.environment.clone(),
                 ));
             
@@ -200,26 +1127,36 @@ impl Interpreter {
                 condition,
                 increment,
                 body,
+                ..
             } => {
-                if let Some(init) = initializer {
-                    self.execute(init)?;
-                }
-                while {
-                    if let Some(cond) = condition {
-                        let result = self.evaluate(cond)?;
-                        self.is_truthy(&result)
-                    } else {
-                        true
-                    }
-                } {
-                    self.execute(body)?;
-                    if let Some(inc) = increment {
-                        self.evaluate(inc)?;
-                    }
+                // The initializer gets its own scope so loop variables don't leak
+                let previous = self.environment.clone();
+                self.environment = Rc::new(RefCell::new(Environment::new(Some(previous.clone()))));
+                let result = self.execute_for(initializer, condition, increment, body);
+                self.environment = previous;
+                result
+            }
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+                ..
+            } => {
+                let iterable = self.evaluate(iterable)?;
+                let mut values = iterate(&iterable)?;
+                while let Some(value) = self.next_value(&mut values)? {
+                    // A fresh scope per iteration, so closures capture that iteration's value
+                    let previous = self.environment.clone();
+                    let mut scope = Environment::new(Some(previous.clone()));
+                    scope.define(name.lexeme.clone(), value);
+                    self.environment = Rc::new(RefCell::new(scope));
+                    let result = self.execute(body);
+                    self.environment = previous;
+                    result?;
                 }
                 Ok(())
             }
-            Stmt::Input { name } => {
+            Stmt::Input { name, .. } => {
                 // Read user input from the console
                 let mut input = String::new();
                 std::io::stdin()
@@ -228,19 +1165,17 @@ impl Interpreter {
                 let input = input.trim().to_string(); // Remove whitespace
 
                 // Try parsing as number, otherwise store as string
-                let value: Arc<dyn Any + Send + Sync> = if let Ok(num) = input.parse::<f64>() {
-                    Arc::new(num) // Store as number if possible
-                } else {
-                    Arc::new(input) // Store as string otherwise
-                };
+                let value = parse_number(&input).unwrap_or_else(|| Arc::new(input));
 
                 self.environment
                     .borrow_mut()
                     .assign(name, value)
-                    .map_err(|e| e.to_string())
+                    .map_err(|e| e.to_string().into())
             }
 
-            Stmt::While { condition, body } => {
+            Stmt::While {
+                condition, body, ..
+            } => {
                 while {
                     let result = self.evaluate(condition)?;
                     self.is_truthy(&result)
@@ -249,7 +1184,7 @@ impl Interpreter {
                 }
                 Ok(())
             }
-            Stmt::Block(statements) => {
+            Stmt::Block { statements, .. } => {
                 let enclosing = self.environment.clone();
                 let new_env = Environment::new(Some(enclosing));
                 self.execute_block(statements, Rc::new(RefCell::new(new_env)))
@@ -259,6 +1194,7 @@ impl Interpreter {
                 condition,
                 then_branch,
                 else_branch,
+                ..
             } => {
                 let condition = self.evaluate(condition)?;
                 if let Some(b) = condition.downcast_ref::<bool>() {
@@ -270,67 +1206,117 @@ impl Interpreter {
                         Ok(())
                     }
                 } else {
-                    Err("Condition must be a boolean.".to_string())
+                    Err("Condition must be a boolean.".to_string().into())
                 }
             }
 
-            Stmt::Var { name, initializer } => {
+            Stmt::Var {
+                name, initializer, ..
+            } => {
                 let value = if let Some(init) = initializer {
                     self.evaluate(init)?
                 } else {
                     Arc::new(())
                 };
-                let cloned_value = if let Some(v) = value.downcast_ref::<f64>() {
+                let cloned_value = if let Some(v) = value.downcast_ref::<i64>() {
+                    Arc::new(*v) as Arc<dyn Any + Send + Sync>
+                } else if let Some(v) = value.downcast_ref::<BigInt>() {
+                    Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
+                } else if let Some(v) = value.downcast_ref::<f64>() {
                     Arc::new(*v) as Arc<dyn Any + Send + Sync>
                 } else if let Some(v) = value.downcast_ref::<String>() {
                     Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
                 } else if let Some(v) = value.downcast_ref::<bool>() {
                     Arc::new(*v) as Arc<dyn Any + Send + Sync>
+                } else if let Some(v) = value.downcast_ref::<Range>() {
+                    Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
+                } else if let Some(v) = value.downcast_ref::<Generator>() {
+                    Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
+                } else if let Some(v) = value.downcast_ref::<Function>() {
+                    Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
+                } else if let Some(v) = value.downcast_ref::<NativeFunction>() {
+                    Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
                 } else {
                     Arc::new(()) as Arc<dyn Any + Send + Sync>
                 };
                 self.environment
                     .borrow_mut()
-                    .define(name.clone(), cloned_value);
+                    .define(name.lexeme.clone(), cloned_value);
                 Ok(())
             }
-            Stmt::Expression { expression } => {
+            Stmt::Expression { expression, .. } => {
                 self.evaluate(expression)?;
                 Ok(())
             }
-            Stmt::Print { expression } => {
+            Stmt::Print { expression, .. } => {
                 let value = self.evaluate(expression)?;
-                let output_line = format!("{}\n", self.stringify(&value));
-                self.output.push_str(&output_line); // <-- Capture output
+                let output_line = format!("{}\n", display_value(&value));
+                self.emit(&output_line); // <-- Capture output
                 Ok(())
             }
             
         }
     }
 
+    fn execute_for(
+        &mut self,
+        initializer: &Option<Box<Stmt>>,
+        condition: &Option<Expr>,
+        increment: &Option<Expr>,
+        body: &Stmt,
+    ) -> Result<(), Unwind> {
+        if let Some(init) = initializer {
+            self.execute(init)?;
+        }
+        while {
+            if let Some(cond) = condition {
+                let result = self.evaluate(cond)?;
+                self.is_truthy(&result)
+            } else {
+                true
+            }
+        } {
+            self.execute(body)?;
+            if let Some(inc) = increment {
+                self.evaluate(inc)?;
+            }
+        }
+        Ok(())
+    }
+
     fn evaluate(&mut self, expr: &Expr) -> Result<Arc<dyn Any + Send + Sync>, String> {
+        self.descend()?;
+        let result = self.evaluate_expr(expr);
+        self.depth -= 1;
+        result
+    }
+
+    fn evaluate_expr(&mut self, expr: &Expr) -> Result<Arc<dyn Any + Send + Sync>, String> {
         match expr {
 
-            Expr::Call { callee, arguments } => {
+            Expr::Call {
+                callee, arguments, ..
+            } => {
                 let function_value = self.evaluate(callee)?;
+
+                if let Some(native) = function_value.downcast_ref::<NativeFunction>() {
+                    let mut args = Vec::new();
+                    for arg in arguments {
+                        args.push(self.evaluate(arg)?);
+                    }
+                    return (native.function)(self, &args);
+                }
             
                 let function = function_value
                     .downcast_ref::<Function>()
-                    .ok_or_else(|| "Runtime error: Expected function, found unsupported type.".to_string())?;
+                    .ok_or_else(|| "Expected function, found unsupported type.".to_string())?;
             
                 let mut args = Vec::new();
                 for arg in arguments {
                     args.push(self.evaluate(arg)?);
                 }
             
-                let result = self.call_function(function, args)?;
-                
-                //  Ensure result is properly unwrapped
-                if let Some(value) = result.downcast_ref::<i64>() {
-                    return Ok(Arc::new(*value)); //  Convert back to expected type
-                }
-                
-                Ok(result) //  Ensure correct type is returned
+                self.call_function(function, args)
             }
             
             Expr::Logical {
@@ -374,14 +1360,14 @@ impl Interpreter {
                 // Ensure the condition is treated as a boolean
                 if let Some(condition_bool) = condition_value.downcast_ref::<bool>() {
                     if *condition_bool {
-                        return self.evaluate(then_branch);
+                        self.evaluate(then_branch)
                     } else if let Some(else_expr) = else_branch {
-                        return self.evaluate(else_expr);
+                        self.evaluate(else_expr)
                     } else {
-                        return Ok(Arc::new(())); // Ensure that the if-expression always returns a value (avoid nil issues)
+                        Ok(Arc::new(())) // Ensure that the if-expression always returns a value (avoid nil issues)
                     }
                 } else {
-                    return Err("Runtime error: Condition must be a boolean.".to_string());
+                    Err("Runtime error: Condition must be a boolean.".to_string())
                 }
             }
             
@@ -390,20 +1376,31 @@ impl Interpreter {
                     TokenType::IDENTIFIER,
                     name.name.lexeme.clone(),
                     TokenLiteral::Identifier(name.name.lexeme.clone()),
+                    name.name.line,
                 );
             
                 match self.environment.borrow().get(&token) {
                     Ok(value) => {
-                        if let Some(v) = value.downcast_ref::<f64>() {
+                        if let Some(v) = value.downcast_ref::<i64>() {
+                            Ok(Arc::new(*v))
+                        } else if let Some(v) = value.downcast_ref::<BigInt>() {
+                            Ok(Arc::new(v.clone()))
+                        } else if let Some(v) = value.downcast_ref::<f64>() {
                             Ok(Arc::new(*v))
                         } else if let Some(v) = value.downcast_ref::<String>() {
                             Ok(Arc::new(v.clone()))
                         } else if let Some(v) = value.downcast_ref::<bool>() {
                             Ok(Arc::new(*v))
-                        } else if value.is::<()>() {
+                        } else if value.is::<()>() || value.is::<TokenLiteral>() {
                             Ok(Arc::new(TokenLiteral::Null)) //  Return `nil` for uninitialized variables
                         } else if let Some(func) = value.downcast_ref::<Function>() {
                             Ok(Arc::new(func.clone())) //  Return the function reference
+                        } else if let Some(native) = value.downcast_ref::<NativeFunction>() {
+                            Ok(Arc::new(native.clone()))
+                        } else if let Some(range) = value.downcast_ref::<Range>() {
+                            Ok(Arc::new(range.clone()))
+                        } else if let Some(generator) = value.downcast_ref::<Generator>() {
+                            Ok(Arc::new(generator.clone()))
                         } else {
                             Err("Unsupported type.".to_string())
                         }
@@ -413,33 +1410,78 @@ impl Interpreter {
             }
             
 
-            Expr::Assign(name, value_expr) => {
+            Expr::Assign {
+                name,
+                value: value_expr,
+            } => {
                 let value = self.evaluate(value_expr)?;
-                let cloned_value = if let Some(v) = value.downcast_ref::<f64>() {
-                    Arc::new(*v) as Arc<dyn Any + Send + Sync>
-                } else if let Some(v) = value.downcast_ref::<String>() {
-                    Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
-                } else if let Some(v) = value.downcast_ref::<bool>() {
-                    Arc::new(*v) as Arc<dyn Any + Send + Sync>
-                } else {
-                    Arc::new(()) as Arc<dyn Any + Send + Sync>
-                };
-                self.environment
-                    .borrow_mut()
-                    .assign(
-                        &Token::new(
-                            TokenType::IDENTIFIER,
-                            name.clone(),
-                            TokenLiteral::Identifier(name.clone()),
-                        ),
-                        cloned_value,
-                    )
-                    .map_err(|e| e.to_string())?;
+                self.assign_variable(name, &value)?;
                 Ok(value)
             }
+            Expr::CompoundAssign {
+                name,
+                operator,
+                value: value_expr,
+            } => {
+                let current = self.read_variable(name)?;
+                let right = self.evaluate(value_expr)?;
+                let value = self.binary_op(&Self::base_operator(operator), current, right)?;
+                self.assign_variable(name, &value)?;
+                Ok(value)
+            }
+            Expr::Increment {
+                name,
+                operator,
+                prefix,
+            } => {
+                let current = self.read_variable(name)?;
+                if !current.is::<f64>() && !current.is::<i64>() && !current.is::<BigInt>() {
+                    return Err(format!("Operand of '{}' must be a number.", operator.lexeme));
+                }
+                let value = self.binary_op(&Self::base_operator(operator), current.clone(), Arc::new(1_i64))?;
+                self.assign_variable(name, &value)?;
+                Ok(if *prefix { value } else { current })
+            }
+            Expr::Range {
+                start,
+                operator,
+                end,
+                step,
+            } => {
+                let low = self.evaluate(start)?;
+                let high = self.evaluate(end)?;
+                let (Some(start), Some(end)) = (integer(&low), integer(&high)) else {
+                    return Err("Range bounds must be integers.".to_string());
+                };
+                let step = match step {
+                    Some(step) => {
+                        let step = self.evaluate(step)?;
+                        integer(&step).ok_or("Range step must be an integer.")?
+                    }
+                    None => 1,
+                };
+                if step == 0 {
+                    return Err("Range step cannot be zero.".to_string());
+                }
+                Ok(Arc::new(Range {
+                    start,
+                    end,
+                    step,
+                    inclusive: operator.token_type == TokenType::DOT_DOT_EQUAL,
+                }))
+            }
+            Expr::Interpolation { parts } => {
+                let mut joined = String::new();
+                for part in parts {
+                    joined.push_str(&display_value(&self.evaluate(part)?));
+                }
+                text(joined)
+            }
             Expr::Literal(lit) => {
                 if let Some(token_literal) = lit.value.downcast_ref::<TokenLiteral>() {
                     match token_literal {
+                        TokenLiteral::Integer(n) => Ok(Arc::new(*n)),
+                        TokenLiteral::BigInteger(n) => Ok(Arc::new(n.clone())),
                         TokenLiteral::Number(n) => Ok(Arc::new(*n)),
                         TokenLiteral::String(s) => Ok(Arc::new(s.clone())),
                         TokenLiteral::Identifier(id) => Ok(Arc::new(id.clone())),
@@ -458,11 +1500,22 @@ impl Interpreter {
 
                 match unary.operator.token_type {
                     TokenType::MINUS => {
+                        if let Some(n) = right.downcast_ref::<i64>() {
+                            return promote(n.checked_neg(), 0, *n, |zero, n| zero - n);
+                        }
+                        if let Some(n) = right.downcast_ref::<BigInt>() {
+                            return big(-n);
+                        }
                         if let Some(n) = right.downcast_ref::<f64>() {
                             return Ok(Arc::new(-*n));
                         }
                         Err("Operand must be a number.".to_string())
                     }
+                    TokenType::TILDE => match (integer(&right), whole(&right)) {
+                        (Some(n), _) => Ok(Arc::new(!n)),
+                        (None, Some(n)) => big(-n - 1),
+                        (None, None) => Err("Operand of '~' must be an integer.".to_string()),
+                    },
                     TokenType::BANG => {
                         if let Some(b) = right.downcast_ref::<bool>() {
                             return Ok(Arc::new(!b));
@@ -477,221 +1530,223 @@ impl Interpreter {
             Expr::Binary(binary) => {
                 let left = self.evaluate(&binary.left)?;
                 let right = self.evaluate(&binary.right)?;
+                self.binary_op(&binary.operator, left, right)
+            }
+        }
+    }
 
-                match binary.operator.token_type {
-                    // In your binary operation handler for PLUS
-                TokenType::PLUS => {
-                    // First, try to handle the case where both are f64
-                    if let (Some(l), Some(r)) = (left.downcast_ref::<f64>(), right.downcast_ref::<f64>()) {
-                        return Ok(Arc::new(l + r));
-                    }
-                    
-                    // Add this new case to handle i64 + i64
-                    if let (Some(l), Some(r)) = (left.downcast_ref::<i64>(), right.downcast_ref::<i64>()) {
-                        return Ok(Arc::new(l + r));
-                    }
-                    
-                    // Add conversion between i64 and f64
-                    if let (Some(l), Some(r)) = (left.downcast_ref::<i64>(), right.downcast_ref::<f64>()) {
-                        return Ok(Arc::new(*l as f64 + r));
-                    }
-                    
-                    if let (Some(l), Some(r)) = (left.downcast_ref::<f64>(), right.downcast_ref::<i64>()) {
-                        return Ok(Arc::new(l + *r as f64));
-                    }
-                    
-                    // String handling remains the same...
-                    if let (Some(l), Some(r)) = (
-                        left.downcast_ref::<String>(),
-                        right.downcast_ref::<String>(),
-                    ) {
-                        return Ok(Arc::new(format!("{}{}", l, r)));
-                    }
-                    
-                    // Mixed string handling remains the same...
-                    if let Some(l) = left.downcast_ref::<String>() {
-                        return Ok(Arc::new(format!("{}{}", l, self.stringify(&right))));
-                    }
-                    if let Some(r) = right.downcast_ref::<String>() {
-                        return Ok(Arc::new(format!("{}{}", self.stringify(&left), r)));
-                    }
-                    
-                    Err("Operands must be two numbers or two strings.".to_string())
+    // The current value of a variable that is about to be updated in place.
+    fn read_variable(&self, name: &Token) -> Result<Arc<dyn Any + Send + Sync>, String> {
+        self.environment
+            .borrow()
+            .get(name)
+            .map_err(|_| format!("Undefined variable '{}'.", name.lexeme))
+    }
+
+    fn assign_variable(&mut self, name: &Token, value: &Arc<dyn Any + Send + Sync>) -> Result<(), String> {
+        let cloned_value = if let Some(v) = value.downcast_ref::<i64>() {
+            Arc::new(*v) as Arc<dyn Any + Send + Sync>
+        } else if let Some(v) = value.downcast_ref::<BigInt>() {
+            Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
+        } else if let Some(v) = value.downcast_ref::<f64>() {
+            Arc::new(*v) as Arc<dyn Any + Send + Sync>
+        } else if let Some(v) = value.downcast_ref::<String>() {
+            Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
+        } else if let Some(v) = value.downcast_ref::<bool>() {
+            Arc::new(*v) as Arc<dyn Any + Send + Sync>
+        } else if let Some(v) = value.downcast_ref::<Range>() {
+            Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
+        } else if let Some(v) = value.downcast_ref::<Generator>() {
+            Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
+        } else if let Some(v) = value.downcast_ref::<Function>() {
+            Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
+        } else if let Some(v) = value.downcast_ref::<NativeFunction>() {
+            Arc::new(v.clone()) as Arc<dyn Any + Send + Sync>
+        } else {
+            Arc::new(()) as Arc<dyn Any + Send + Sync>
+        };
+        self.environment
+            .borrow_mut()
+            .assign(name, cloned_value)
+            .map_err(|e| e.to_string())
+    }
+
+    // `+=` or `++` as the `+` it applies, keeping the original lexeme for error messages.
+    fn base_operator(operator: &Token) -> Token {
+        Token {
+            token_type: operator.token_type.arithmetic_base().unwrap_or(operator.token_type),
+            ..operator.clone()
+        }
+    }
+
+    // `left <operator> right` for the arithmetic, comparison and equality operators.
+    fn binary_op(
+        &self,
+        operator: &Token,
+        left: Arc<dyn Any + Send + Sync>,
+        right: Arc<dyn Any + Send + Sync>,
+    ) -> Result<Arc<dyn Any + Send + Sync>, String> {
+        match operator.token_type {
+            TokenType::PLUS => {
+                match numbers(&left, &right) {
+                    Some(Numbers::Int(l, r)) => return promote(l.checked_add(r), l, r, |l, r| l + r),
+                    Some(Numbers::Big(l, r)) => return big(l + r),
+                    Some(Numbers::Float(l, r)) => return Ok(Arc::new(l + r)),
+                    None => {}
                 }
 
-                    TokenType::MINUS => {
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<f64>(), right.downcast_ref::<f64>()) {
-                            return Ok(Arc::new(l - r));
-                        }
-                        
-                        // Add this new case to handle i64 + i64
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<i64>(), right.downcast_ref::<i64>()) {
-                            return Ok(Arc::new(l - r));
-                        }
-                        
-                        // Add conversion between i64 and f64
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<i64>(), right.downcast_ref::<f64>()) {
-                            return Ok(Arc::new(*l as f64 - r));
-                        }
-                        
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<f64>(), right.downcast_ref::<i64>()) {
-                            return Ok(Arc::new(l - *r as f64));
-                        }if let (Some(l), Some(r)) = (left.downcast_ref::<f64>(), right.downcast_ref::<f64>()) {
-                            return Ok(Arc::new(l - r));
-                        }
-                        
-                        // Add this new case to handle i64 + i64
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<i64>(), right.downcast_ref::<i64>()) {
-                            return Ok(Arc::new(l * r));
-                        }
-                        
-                        // Add conversion between i64 and f64
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<i64>(), right.downcast_ref::<f64>()) {
-                            return Ok(Arc::new(*l as f64 * r));
-                        }
-                        
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<f64>(), right.downcast_ref::<i64>()) {
-                            return Ok(Arc::new(l * *r as f64));
-                        }
-                        if let (Some(l), Some(r)) =
-                            (left.downcast_ref::<f64>(), right.downcast_ref::<f64>())
-                        {
-                            return Ok(Arc::new(l - r));
-                        }
-                        Err("Operands must be numbers.".to_string())
-                    }
-                    TokenType::STAR => {
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<f64>(), right.downcast_ref::<f64>()) {
-                            return Ok(Arc::new(l * r));
-                        }
-                        
-                        // Add this new case to handle i64 + i64
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<i64>(), right.downcast_ref::<i64>()) {
-                            return Ok(Arc::new(l * r));
-                        }
-                        
-                        // Add conversion between i64 and f64
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<i64>(), right.downcast_ref::<f64>()) {
-                            return Ok(Arc::new(*l as f64 * r));
-                        }
-                        
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<f64>(), right.downcast_ref::<i64>()) {
-                            return Ok(Arc::new(l * *r as f64));
-                        }
-                        if let (Some(l), Some(r)) =
-                            (left.downcast_ref::<f64>(), right.downcast_ref::<f64>())
-                        {
-                            return Ok(Arc::new(l * r));
-                        }
-                        Err("Operands must be numbers.".to_string())
-                    }
-                    TokenType::SLASH => {
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<f64>(), right.downcast_ref::<f64>()) {
-                            return Ok(Arc::new(l / r));
-                        }
-                        
-                        // Add this new case to handle i64 + i64
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<i64>(), right.downcast_ref::<i64>()) {
-                            return Ok(Arc::new(l / r));
-                        }
-                        
-                        // Add conversion between i64 and f64
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<i64>(), right.downcast_ref::<f64>()) {
-                            return Ok(Arc::new(*l as f64 / r));
-                        }
-                        
-                        if let (Some(l), Some(r)) = (left.downcast_ref::<f64>(), right.downcast_ref::<i64>()) {
-                            return Ok(Arc::new(l / *r as f64));
-                        }
-                        if let (Some(l), Some(r)) =
-                            (left.downcast_ref::<f64>(), right.downcast_ref::<f64>())
-                        {
-                            if *r == 0.0 {
-                                return Err("Division by zero.".to_string());
-                            }
-                            return Ok(Arc::new(l / r));
-                        }
-                        Err("Operands must be numbers.".to_string())
-                    }
+                if let (Some(l), Some(r)) = (
+                    left.downcast_ref::<String>(),
+                    right.downcast_ref::<String>(),
+                ) {
+                    return text(format!("{}{}", l, r));
+                }
 
-                    TokenType::EQUAL_EQUAL => {
-                        if let (Some(l), Some(r)) =
-                            (left.downcast_ref::<f64>(), right.downcast_ref::<f64>())
-                        {
-                            return Ok(Arc::new(l == r));
-                        }
-                        if let (Some(l), Some(r)) = (
-                            left.downcast_ref::<String>(),
-                            right.downcast_ref::<String>(),
-                        ) {
-                            return Ok(Arc::new(l == r));
-                        }
-                        Err("Operands must be two numbers or two strings.".to_string())
-                    }
-                    TokenType::BANG_EQUAL => {
-                        if let (Some(l), Some(r)) =
-                            (left.downcast_ref::<f64>(), right.downcast_ref::<f64>())
-                        {
-                            return Ok(Arc::new(l != r));
-                        }
-                        if let (Some(l), Some(r)) = (
-                            left.downcast_ref::<String>(),
-                            right.downcast_ref::<String>(),
-                        ) {
-                            return Ok(Arc::new(l != r));
-                        }
-                        Err("Operands must be two numbers or two strings.".to_string())
-                    }
+                // A string on either side turns the other operand into text
+                if let Some(l) = left.downcast_ref::<String>() {
+                    return text(format!("{}{}", l, display_value(&right)));
+                }
+                if let Some(r) = right.downcast_ref::<String>() {
+                    return text(format!("{}{}", display_value(&left), r));
+                }
 
-                    TokenType::GREATER => {
-                        if let (Some(l), Some(r)) =
-                            (left.downcast_ref::<f64>(), right.downcast_ref::<f64>())
-                        {
-                            return Ok(Arc::new(l > r));
-                        }
-                        Err("Operands must be numbers.".to_string())
+                Err("Operands must be two numbers or two strings.".to_string())
+            }
+            TokenType::MINUS => match numbers(&left, &right) {
+                Some(Numbers::Int(l, r)) => promote(l.checked_sub(r), l, r, |l, r| l - r),
+                Some(Numbers::Big(l, r)) => big(l - r),
+                Some(Numbers::Float(l, r)) => Ok(Arc::new(l - r)),
+                None => Err("Operands must be numbers.".to_string()),
+            },
+            TokenType::STAR => match numbers(&left, &right) {
+                Some(Numbers::Int(l, r)) => promote(l.checked_mul(r), l, r, |l, r| l * r),
+                Some(Numbers::Big(l, r)) => big(l * r),
+                Some(Numbers::Float(l, r)) => Ok(Arc::new(l * r)),
+                None => Err("Operands must be numbers.".to_string()),
+            },
+            // `/` always gives a float (`7 / 2` is 3.5, `6 / 2` is 3.0); `~/` is the
+            // integer division.
+            TokenType::SLASH => match numbers(&left, &right) {
+                Some(Numbers::Int(_, 0)) => Err("Division by zero.".to_string()),
+                Some(Numbers::Float(_, 0.0)) => Err("Division by zero.".to_string()),
+                Some(Numbers::Int(l, r)) => Ok(Arc::new(l as f64 / r as f64)),
+                Some(Numbers::Big(_, r)) if r.is_zero() => Err("Division by zero.".to_string()),
+                Some(Numbers::Big(l, r)) => Ok(Arc::new(big_ratio(&l, &r))),
+                Some(Numbers::Float(l, r)) => Ok(Arc::new(l / r)),
+                None => Err("Operands must be numbers.".to_string()),
+            },
+            // Both round the quotient down, so `a == (a ~/ b) * b + a % b` and a remainder
+            // takes the sign of the divisor: `-7 ~/ 2` is -4 and `-7 % 2` is 1. `~/` always
+            // gives an integer, `%` a float if either operand is one.
+            TokenType::PERCENT | TokenType::TILDE_SLASH => {
+                let want_quotient = operator.token_type == TokenType::TILDE_SLASH;
+                match numbers(&left, &right) {
+                    Some(Numbers::Int(_, 0)) => Err("Division by zero.".to_string()),
+                    Some(Numbers::Float(_, 0.0)) => Err("Division by zero.".to_string()),
+                    Some(Numbers::Int(l, r)) if l == i64::MIN && r == -1 => {
+                        big(if want_quotient { -BigInt::from(l) } else { BigInt::zero() })
                     }
-                    TokenType::GREATER_EQUAL => {
-                        if let (Some(l), Some(r)) =
-                            (left.downcast_ref::<f64>(), right.downcast_ref::<f64>())
-                        {
-                            return Ok(Arc::new(l >= r));
-                        }
-                        Err("Operands must be numbers.".to_string())
+                    Some(Numbers::Int(l, r)) => {
+                        let (quotient, remainder) = l.div_mod_floor(&r);
+                        Ok(Arc::new(if want_quotient { quotient } else { remainder }))
+                    }
+                    Some(Numbers::Big(_, r)) if r.is_zero() => Err("Division by zero.".to_string()),
+                    Some(Numbers::Big(l, r)) => {
+                        let (quotient, remainder) = l.div_mod_floor(&r);
+                        big(if want_quotient { quotient } else { remainder })
                     }
-                    TokenType::LESS => {
-                        if let (Some(l), Some(r)) =
-                            (left.downcast_ref::<f64>(), right.downcast_ref::<f64>())
-                        {
-                            return Ok(Arc::new(l < r));
+                    Some(Numbers::Float(l, r)) if want_quotient => float_quotient((l / r).floor()),
+                    Some(Numbers::Float(l, r)) => {
+                        let remainder = l % r;
+                        if remainder != 0.0 && (remainder < 0.0) != (r < 0.0) {
+                            return Ok(Arc::new(remainder + r));
                         }
-                        Err("Operands must be numbers.".to_string())
+                        Ok(Arc::new(remainder))
                     }
-                    TokenType::LESS_EQUAL => {
-                        if let (Some(l), Some(r)) =
-                            (left.downcast_ref::<f64>(), right.downcast_ref::<f64>())
-                        {
-                            return Ok(Arc::new(l <= r));
+                    None => Err("Operands must be numbers.".to_string()),
+                }
+            }
+            // Whole numbers written as floats (`4.0 & 1`) are accepted; the result is an integer.
+            // Big integers act as if they had infinitely many sign bits, as Python's do.
+            TokenType::AMPERSAND
+            | TokenType::PIPE
+            | TokenType::CARET
+            | TokenType::LESS_LESS
+            | TokenType::GREATER_GREATER => {
+                if let (Some(l), Some(r)) = (integer(&left), integer(&right)) {
+                    match operator.token_type {
+                        TokenType::AMPERSAND => return Ok(Arc::new(l & r)),
+                        TokenType::PIPE => return Ok(Arc::new(l | r)),
+                        TokenType::CARET => return Ok(Arc::new(l ^ r)),
+                        TokenType::LESS_LESS if (0..64).contains(&r) && (l << r) >> r == l => {
+                            return Ok(Arc::new(l << r))
                         }
-                        Err("Operands must be numbers.".to_string())
+                        TokenType::GREATER_GREATER if r >= 0 => return Ok(Arc::new(l >> r.min(63))),
+                        _ => {}
                     }
-                    _ => Err("Unknown binary operator.".to_string()),
+                }
+                let (Some(l), Some(r)) = (whole(&left), whole(&right)) else {
+                    return Err(format!("Operands of '{}' must be integers.", operator.lexeme));
+                };
+                match operator.token_type {
+                    TokenType::AMPERSAND => big(l & r),
+                    TokenType::PIPE => big(l | r),
+                    TokenType::CARET => big(l ^ r),
+                    TokenType::LESS_LESS => big_shift(l, r, true),
+                    _ => big_shift(l, r, false),
                 }
             }
-        }
-    }
+            // An integer to a non-negative integer power stays an integer. Negative or
+            // fractional exponents give floats following IEEE: `2 ** -1` is 0.5, `(-8) ** 0.5` is NaN.
+            TokenType::STAR_STAR => match numbers(&left, &right) {
+                Some(Numbers::Int(l, r)) if r >= 0 => match u32::try_from(r).ok().and_then(|r| l.checked_pow(r)) {
+                    Some(n) => Ok(Arc::new(n)),
+                    None => big_pow(BigInt::from(l), BigInt::from(r)),
+                },
+                Some(Numbers::Int(l, r)) => Ok(Arc::new((l as f64).powf(r as f64))),
+                Some(Numbers::Big(l, r)) if !r.is_negative() => big_pow(l, r),
+                Some(Numbers::Big(l, r)) => Ok(Arc::new(
+                    l.to_f64().unwrap_or(f64::NAN).powf(r.to_f64().unwrap_or(f64::NAN)),
+                )),
+                Some(Numbers::Float(l, r)) => Ok(Arc::new(l.powf(r))),
+                None => Err("Operands must be numbers.".to_string()),
+            },
 
-    fn stringify(&self, value: &Arc<dyn Any + Send + Sync>) -> String {
-        if let Some(v) = value.downcast_ref::<i64>() {
-            return v.to_string();
-        } else if let Some(v) = value.downcast_ref::<f64>() {
-            return v.to_string();
-        } else if let Some(v) = value.downcast_ref::<String>() {
-            return v.clone();
-        } else if value.downcast_ref::<()>().is_some() {
-            return "nil".to_string();
+            // Numbers compare by value whatever their type, so `1 == 1.0`.
+            TokenType::EQUAL_EQUAL | TokenType::BANG_EQUAL => {
+                let equal = match numbers(&left, &right) {
+                    Some(Numbers::Int(l, r)) => l == r,
+                    Some(Numbers::Big(l, r)) => l == r,
+                    Some(Numbers::Float(l, r)) => l == r,
+                    None => match (left.downcast_ref::<String>(), right.downcast_ref::<String>()) {
+                        (Some(l), Some(r)) => l == r,
+                        _ => return Err("Operands must be two numbers or two strings.".to_string()),
+                    },
+                };
+                Ok(Arc::new(equal == (operator.token_type == TokenType::EQUAL_EQUAL)))
+            }
+
+            TokenType::GREATER | TokenType::GREATER_EQUAL | TokenType::LESS | TokenType::LESS_EQUAL => {
+                let ordering = match numbers(&left, &right) {
+                    Some(Numbers::Int(l, r)) => l.partial_cmp(&r),
+                    Some(Numbers::Big(l, r)) => l.partial_cmp(&r),
+                    Some(Numbers::Float(l, r)) => l.partial_cmp(&r),
+                    None => return Err("Operands must be numbers.".to_string()),
+                };
+                // NaN compares false with everything.
+                let Some(ordering) = ordering else {
+                    return Ok(Arc::new(false));
+                };
+                Ok(Arc::new(match operator.token_type {
+                    TokenType::GREATER => ordering.is_gt(),
+                    TokenType::GREATER_EQUAL => ordering.is_ge(),
+                    TokenType::LESS => ordering.is_lt(),
+                    _ => ordering.is_le(),
+                }))
+            }
+            _ => Err("Unknown binary operator.".to_string()),
         }
-        "(Unknown type)".to_string() // Default case
     }
+
     
 }
@@ -0,0 +1,20 @@
+// The interpreter as a library, for the `rust` binary in main.rs and for the fuzz targets
+// in fuzz/.
+pub mod error;
+pub mod token;
+pub mod astprinter;
+pub mod expr;
+pub mod interpreter;
+pub mod parser;
+pub mod stmt;
+pub mod environment;
+pub mod formatter;
+pub mod cst;
+pub mod linter;
+pub mod typechecker;
+pub mod lsp;
+pub mod debugger;
+pub mod dap;
+pub mod profiler;
+pub mod coverage;
+pub mod testrunner;
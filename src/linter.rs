@@ -0,0 +1,503 @@
+use crate::expr::Expr;
+use crate::parser::Parser;
+use crate::stmt::{Pattern, Stmt};
+use crate::token::{Comment, Tokensizer};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    UnusedVariable,
+    Shadowing,
+    UnreachableCode,
+    SelfAssignment,
+    ConstantCondition,
+    EmptyBlock,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::UnusedVariable,
+        Rule::Shadowing,
+        Rule::UnreachableCode,
+        Rule::SelfAssignment,
+        Rule::ConstantCondition,
+        Rule::EmptyBlock,
+    ];
+
+    /// The name used on the command line and in `// lint: allow(...)` comments.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::Shadowing => "shadowing",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::SelfAssignment => "self-assignment",
+            Rule::ConstantCondition => "constant-condition",
+            Rule::EmptyBlock => "empty-block",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.iter().copied().find(|rule| rule.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub rule: Rule,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] warning[{}]: {}", self.line, self.rule.name(), self.message)
+    }
+}
+
+/// Which rules run. Everything is enabled by default.
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    pub disabled: Vec<Rule>,
+}
+
+/// Lints a whole source file. Warnings come back sorted by line.
+///
+/// A rule can be silenced for one line with `// lint: allow(rule, ...)`, either at the end
+/// of that line or on the line above it, and for the whole file with `// lint: allow-file(rule)`.
+pub fn lint_source(source: &str, config: &LintConfig) -> Result<Vec<Warning>, String> {
+    let mut tokenizer = Tokensizer::new(source.to_string());
+    let tokens = tokenizer.tokenize();
+    let comments = tokenizer.comments().to_vec();
+
+    let statements = Parser::new(tokens)
+        .parse()
+        .ok_or_else(|| "Parsing failed due to syntax errors.".to_string())?;
+
+    let mut linter = Linter::new(&comments);
+    linter.lint(&statements);
+
+    let suppressions = Suppressions::from_comments(&comments);
+    let mut warnings: Vec<Warning> = linter
+        .warnings
+        .into_iter()
+        .filter(|w| !config.disabled.contains(&w.rule) && !suppressions.allows(w.rule, w.line))
+        .collect();
+    warnings.sort_by_key(|w| w.line);
+    Ok(warnings)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DeclKind {
+    Variable,
+    Function,
+    Parameter,
+}
+
+struct Declaration {
+    name: String,
+    line: usize,
+    kind: DeclKind,
+    used: bool,
+}
+
+#[derive(Default)]
+struct Scope<'a> {
+    names: HashMap<String, usize>, // name -> index into `Linter::declarations`
+    declared: Vec<usize>,
+    functions: Vec<&'a Stmt>, // bodies are checked when the scope closes, so they see later globals
+}
+
+pub struct Linter<'a> {
+    comments: &'a [Comment],
+    scopes: Vec<Scope<'a>>,
+    declarations: Vec<Declaration>,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(comments: &'a [Comment]) -> Self {
+        Linter {
+            comments,
+            scopes: Vec::new(),
+            declarations: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn lint(&mut self, statements: &'a [Stmt]) {
+        self.scopes.push(Scope::default());
+        self.statements(statements);
+        self.end_scope();
+    }
+
+    fn statements(&mut self, statements: &'a [Stmt]) {
+        let mut returned = false;
+        for stmt in statements {
+            if returned {
+                self.warn(Rule::UnreachableCode, stmt.line(), "Unreachable code after 'return'.".to_string());
+                returned = false; // one warning per dead stretch is enough
+            }
+            self.statement(stmt);
+            if Self::always_returns(stmt) {
+                returned = true;
+            }
+        }
+    }
+
+    fn statement(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::Expression { expression, .. } | Stmt::Print { expression, .. } => {
+                self.expression(expression)
+            }
+            Stmt::Var {
+                name, initializer, ..
+            } => {
+                if let Some(init) = initializer {
+                    self.expression(init);
+                }
+                self.declare(&name.lexeme, name.line, DeclKind::Variable);
+            }
+            Stmt::Block {
+                statements,
+                line,
+                end_line,
+                ..
+            } => {
+                if statements.is_empty() && !self.has_comment_between(*line, *end_line) {
+                    self.warn(Rule::EmptyBlock, *line, "Empty block.".to_string());
+                }
+                self.scopes.push(Scope::default());
+                self.statements(statements);
+                self.end_scope();
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                line,
+                ..
+            } => {
+                if Self::is_constant(condition) {
+                    self.warn(Rule::ConstantCondition, *line, "Condition is always the same.".to_string());
+                }
+                self.expression(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.expression(condition);
+                self.statement(body);
+            }
+            Stmt::Input { name, .. } => {
+                self.resolve(&name.lexeme, false);
+            }
+            // A binding nothing reads is reported; `_` says the same thing.
+            Stmt::Match { subject, arms, .. } => {
+                self.expression(subject);
+                for arm in arms {
+                    self.scopes.push(Scope::default());
+                    match &arm.pattern {
+                        Pattern::Literal { value } => self.expression(value),
+                        Pattern::Range { start, end, .. } => {
+                            self.expression(start);
+                            self.expression(end);
+                        }
+                        Pattern::Binding { name } => self.declare(&name.lexeme, name.line, DeclKind::Variable),
+                        Pattern::Wildcard { .. } => {}
+                    }
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.statement(&arm.body);
+                    self.end_scope();
+                }
+            }
+            // The loop variable is treated like a parameter: never warned about when unused.
+            Stmt::ForIn {
+                name, iterable, body, ..
+            } => {
+                self.expression(iterable);
+                self.scopes.push(Scope::default());
+                self.declare(&name.lexeme, name.line, DeclKind::Parameter);
+                self.statement(body);
+                self.end_scope();
+            }
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                self.scopes.push(Scope::default());
+                if let Some(init) = initializer {
+                    self.statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.expression(condition);
+                }
+                if let Some(increment) = increment {
+                    self.expression(increment);
+                }
+                self.statement(body);
+                self.end_scope();
+            }
+            Stmt::Function { name, .. } => {
+                self.declare(&name.lexeme, name.line, DeclKind::Function);
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.functions.push(stmt);
+                }
+            }
+            Stmt::Return { value, .. } | Stmt::Yield { value, .. } => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+        }
+    }
+
+    fn function_body(&mut self, function: &'a Stmt) {
+        if let Stmt::Function {
+            name,
+            params,
+            body,
+            end_line,
+            ..
+        } = function
+        {
+            if body.is_empty() && !self.has_comment_between(name.line, *end_line) {
+                self.warn(Rule::EmptyBlock, name.line, "Empty block.".to_string());
+            }
+            self.scopes.push(Scope::default());
+            for param in params {
+                self.declare(&param.lexeme, param.line, DeclKind::Parameter);
+            }
+            self.statements(body);
+            self.end_scope();
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Binary(b) => {
+                self.expression(&b.left);
+                self.expression(&b.right);
+            }
+            Expr::Grouping(g) => self.expression(&g.expression),
+            Expr::Literal(_) => {}
+            Expr::Unary(u) => self.expression(&u.right),
+            Expr::Variable(v) => self.resolve(&v.name.lexeme, true),
+            Expr::Assign { name, value } => {
+                if let Expr::Variable(target) = Self::ungroup(value) {
+                    if target.name.lexeme == name.lexeme {
+                        self.warn(
+                            Rule::SelfAssignment,
+                            name.line,
+                            format!("'{}' is assigned to itself.", name.lexeme),
+                        );
+                    }
+                }
+                self.expression(value);
+                self.resolve(&name.lexeme, false);
+            }
+            // `x += 1` reads `x` just like `x = x + 1` does.
+            Expr::CompoundAssign { name, value, .. } => {
+                self.expression(value);
+                self.resolve(&name.lexeme, true);
+            }
+            Expr::Increment { name, .. } => self.resolve(&name.lexeme, true),
+            Expr::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expression(condition);
+                self.expression(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.expression(else_branch);
+                }
+            }
+            Expr::Logical { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.expression(callee);
+                for arg in arguments {
+                    self.expression(arg);
+                }
+            }
+            Expr::Interpolation { parts } => {
+                for part in parts {
+                    self.expression(part);
+                }
+            }
+            Expr::Range { start, end, step, .. } => {
+                self.expression(start);
+                self.expression(end);
+                if let Some(step) = step {
+                    self.expression(step);
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &str, line: usize, kind: DeclKind) {
+        // Parameters reusing a global's name are too common in scripts to be worth a warning.
+        let previous = self.lookup(name).filter(|_| kind != DeclKind::Parameter);
+        if let Some(previous) = previous {
+            let previous = &self.declarations[previous];
+            let what = match kind {
+                DeclKind::Function => "Function",
+                _ => "Variable",
+            };
+            let message = format!(
+                "{} '{}' shadows an earlier declaration on line {}.",
+                what, name, previous.line
+            );
+            self.warn(Rule::Shadowing, line, message);
+        }
+
+        let index = self.declarations.len();
+        self.declarations.push(Declaration {
+            name: name.to_string(),
+            line,
+            kind,
+            used: false,
+        });
+        let scope = self.scopes.last_mut().expect("linter always has a scope");
+        scope.names.insert(name.to_string(), index);
+        scope.declared.push(index);
+    }
+
+    // Marks the nearest declaration of `name` as read. Unknown names are left to the runtime.
+    fn resolve(&mut self, name: &str, read: bool) {
+        if let Some(index) = self.lookup(name) {
+            if read {
+                self.declarations[index].used = true;
+            }
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.names.get(name).copied())
+    }
+
+    fn end_scope(&mut self) {
+        // Function bodies run after the surrounding code, so check them once the scope is complete.
+        while let Some(function) = self.scopes.last_mut().and_then(|scope| scope.functions.pop()) {
+            self.function_body(function);
+        }
+
+        let scope = self.scopes.pop().expect("linter always has a scope");
+        for index in scope.declared {
+            let decl = &self.declarations[index];
+            if decl.kind == DeclKind::Variable && !decl.used {
+                let message = format!("Variable '{}' is declared but never read.", decl.name);
+                let line = decl.line;
+                self.warn(Rule::UnusedVariable, line, message);
+            }
+        }
+    }
+
+    fn warn(&mut self, rule: Rule, line: usize, message: String) {
+        self.warnings.push(Warning {
+            rule,
+            line,
+            message,
+        });
+    }
+
+    fn has_comment_between(&self, start: usize, end: usize) -> bool {
+        self.comments
+            .iter()
+            .any(|comment| comment.line >= start && comment.line <= end)
+    }
+
+    fn always_returns(stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::Return { .. } => true,
+            Stmt::Block { statements, .. } => statements.iter().any(Self::always_returns),
+            Stmt::If {
+                then_branch,
+                else_branch: Some(else_branch),
+                ..
+            } => Self::always_returns(then_branch) && Self::always_returns(else_branch),
+            _ => false,
+        }
+    }
+
+    // True for expressions built only from literals, e.g. `true` or `(1 < 2)`.
+    fn is_constant(expr: &Expr) -> bool {
+        match expr {
+            Expr::Literal(_) => true,
+            Expr::Grouping(g) => Self::is_constant(&g.expression),
+            Expr::Unary(u) => Self::is_constant(&u.right),
+            Expr::Binary(b) => Self::is_constant(&b.left) && Self::is_constant(&b.right),
+            Expr::Logical { left, right, .. } => Self::is_constant(left) && Self::is_constant(right),
+            Expr::Interpolation { parts } => parts.iter().all(Self::is_constant),
+            _ => false,
+        }
+    }
+
+    fn ungroup(expr: &Expr) -> &Expr {
+        match expr {
+            Expr::Grouping(g) => Self::ungroup(&g.expression),
+            _ => expr,
+        }
+    }
+}
+
+// Rules silenced by `// lint: allow(...)` and `// lint: allow-file(...)` comments.
+struct Suppressions {
+    lines: Vec<(usize, Rule)>,
+    file: Vec<Rule>,
+}
+
+impl Suppressions {
+    fn from_comments(comments: &[Comment]) -> Self {
+        let mut suppressions = Suppressions {
+            lines: Vec::new(),
+            file: Vec::new(),
+        };
+        for comment in comments {
+            let text = comment.text.trim_start_matches('/').trim();
+            let Some(directive) = text.strip_prefix("lint:") else {
+                continue;
+            };
+            let directive = directive.trim();
+            let (file_wide, list) = if let Some(list) = directive.strip_prefix("allow-file(") {
+                (true, list)
+            } else if let Some(list) = directive.strip_prefix("allow(") {
+                (false, list)
+            } else {
+                continue;
+            };
+            let list = list.split(')').next().unwrap_or("");
+            // An own-line comment covers the line below it; a trailing one covers its own line.
+            let line = if comment.trailing { comment.line } else { comment.line + 1 };
+            for rule in list.split(',').filter_map(|name| Rule::from_name(name.trim())) {
+                if file_wide {
+                    suppressions.file.push(rule);
+                } else {
+                    suppressions.lines.push((line, rule));
+                }
+            }
+        }
+        suppressions
+    }
+
+    fn allows(&self, rule: Rule, line: usize) -> bool {
+        self.file.contains(&rule) || self.lines.contains(&(line, rule))
+    }
+}
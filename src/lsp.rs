@@ -0,0 +1,794 @@
+// Language server over stdio. `LanguageServer` maps each incoming JSON-RPC message to the
+// messages it sends back, so it can be driven in-process by a scripted client; `serve`
+// adds the `Content-Length` framing used on stdin/stdout.
+use crate::cst;
+use crate::expr::Expr;
+use crate::interpreter;
+use crate::linter::{self, LintConfig};
+use crate::parser::Parser;
+use crate::stmt::{Pattern, Stmt};
+use crate::token::{Span, Token, Tokensizer};
+use crate::typechecker;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::panic;
+
+const KEYWORDS: [&str; 17] = [
+    "and", "else", "false", "for", "fun", "if", "in", "match", "nil", "or", "return", "scan",
+    "true", "var", "while", "write", "yield",
+];
+
+// LSP enum values used below.
+const SEVERITY_ERROR: u32 = 1;
+const SEVERITY_WARNING: u32 = 2;
+const SYMBOL_FUNCTION: u32 = 12;
+const SYMBOL_VARIABLE: u32 = 13;
+const COMPLETION_FUNCTION: u32 = 3;
+const COMPLETION_VARIABLE: u32 = 6;
+const COMPLETION_KEYWORD: u32 = 14;
+
+/// Reads framed messages from `reader` and answers on `writer` until `exit`.
+/// Returns the process exit code: 0 if `shutdown` came first, 1 otherwise.
+pub fn serve<R: BufRead, W: Write>(mut reader: R, mut writer: W) -> i32 {
+    let mut server = LanguageServer::new();
+    loop {
+        let message = match read_message(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => return 1, // client went away without `exit`
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                let reply = error_response(Value::Null, -32700, &err.to_string());
+                if write_message(&mut writer, &reply).is_err() {
+                    return 1;
+                }
+                continue;
+            }
+            Err(_) => return 1,
+        };
+        for reply in server.handle(&message) {
+            if write_message(&mut writer, &reply).is_err() {
+                return 1;
+            }
+        }
+        if server.exited {
+            return if server.shutdown_requested { 0 } else { 1 };
+        }
+    }
+}
+
+/// Reads one `Content-Length` framed message. `Ok(None)` means end of input.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header."));
+    };
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+fn response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_response(id: Value, code: i32, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+#[derive(Default)]
+pub struct LanguageServer {
+    documents: HashMap<String, Analysis>, // keyed by URI
+    shutdown_requested: bool,
+    exited: bool,
+}
+
+impl LanguageServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles one request or notification and returns the responses and
+    /// notifications to send back, in order.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let id = message.get("id").cloned();
+
+        if self.shutdown_requested && method != "exit" {
+            return id
+                .map(|id| error_response(id, -32600, "Server is shutting down."))
+                .into_iter()
+                .collect();
+        }
+
+        match method {
+            "initialize" => vec![response(id.unwrap_or(Value::Null), capabilities())],
+            "shutdown" => {
+                self.shutdown_requested = true;
+                vec![response(id.unwrap_or(Value::Null), Value::Null)]
+            }
+            "exit" => {
+                self.exited = true;
+                Vec::new()
+            }
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                self.update(document["uri"].as_str().unwrap_or(""), document["text"].as_str().unwrap_or(""))
+            }
+            "textDocument/didChange" => {
+                // Only full-document sync is advertised, so the last change holds the whole text.
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                match params["contentChanges"].as_array().and_then(|changes| changes.last()) {
+                    Some(change) => self.update(uri, change["text"].as_str().unwrap_or("")),
+                    None => Vec::new(),
+                }
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                self.documents.remove(uri);
+                vec![notification(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                )]
+            }
+            "textDocument/hover"
+            | "textDocument/definition"
+            | "textDocument/documentSymbol"
+            | "textDocument/completion" => {
+                let id = id.unwrap_or(Value::Null);
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                let Some(analysis) = self.documents.get(uri) else {
+                    return vec![error_response(id, -32602, &format!("Unknown document '{}'.", uri))];
+                };
+                let position = &params["position"];
+                let offset = analysis.lines.offset(
+                    position["line"].as_u64().unwrap_or(0) as usize,
+                    position["character"].as_u64().unwrap_or(0) as usize,
+                );
+                let result = match method {
+                    "textDocument/hover" => analysis.hover(offset),
+                    "textDocument/definition" => analysis.definition(uri, offset),
+                    "textDocument/documentSymbol" => analysis.document_symbols(),
+                    _ => analysis.completion(offset),
+                };
+                vec![response(id, result)]
+            }
+            _ => match id {
+                // Unknown notifications (`initialized`, `$/cancelRequest`, ...) are ignored.
+                Some(id) => vec![error_response(id, -32601, &format!("Method '{}' not found.", method))],
+                None => Vec::new(),
+            },
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: &str) -> Vec<Value> {
+        let analysis = Analysis::new(text);
+        let diagnostics = json!({ "uri": uri, "diagnostics": analysis.diagnostics });
+        self.documents.insert(uri.to_string(), analysis);
+        vec![notification("textDocument/publishDiagnostics", diagnostics)]
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "documentSymbolProvider": true,
+            "completionProvider": {}
+        },
+        "serverInfo": { "name": "aoi" }
+    })
+}
+
+// Converts between byte offsets and LSP positions (zero-based line, UTF-16 column).
+struct LineIndex {
+    text: String,
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(text: &str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex {
+            text: text.to_string(),
+            starts,
+        }
+    }
+
+    fn position(&self, offset: usize) -> Value {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let character = self.text[self.starts[line]..offset].encode_utf16().count();
+        json!({ "line": line, "character": character })
+    }
+
+    fn offset(&self, line: usize, character: usize) -> usize {
+        let Some(&start) = self.starts.get(line) else {
+            return self.text.len();
+        };
+        let mut units = 0;
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= character || c == '\n' {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+
+    fn range(&self, span: Span) -> Value {
+        json!({ "start": self.position(span.start), "end": self.position(span.end) })
+    }
+
+    // The whole of a one-based source line, for diagnostics that only know their line.
+    fn line_range(&self, line: usize) -> Value {
+        let index = line.saturating_sub(1).min(self.starts.len() - 1);
+        let start = self.starts[index];
+        let end = self.text[start..].find('\n').map_or(self.text.len(), |i| start + i);
+        self.range(Span { start, end })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DeclKind {
+    Variable,
+    Function,
+    Parameter,
+}
+
+struct Declaration {
+    name: String,
+    kind: DeclKind,
+    span: Span,   // the name token
+    line: usize,
+    end_line: usize,
+    scope: (usize, usize), // first and last line the name can be visible on
+    parent: Option<usize>, // enclosing function
+    detail: String,        // shown on hover, e.g. `fun add(a: num, b: num): num`
+    doc: Option<String>,   // from `///` comments, shown under the detail
+}
+
+// Everything the server knows about one open document.
+struct Analysis {
+    lines: LineIndex,
+    diagnostics: Vec<Value>,
+    declarations: Vec<Declaration>,
+    references: Vec<(Span, usize)>, // identifier occurrence -> declaration
+}
+
+impl Analysis {
+    fn new(text: &str) -> Self {
+        let mut analysis = Analysis {
+            lines: LineIndex::new(text),
+            diagnostics: Vec::new(),
+            declarations: Vec::new(),
+            references: Vec::new(),
+        };
+        // Nothing is known to panic here any more (the fuzz targets check the tokenizer, parser
+        // and type checker), but analysis also runs the linter and the resolver below. A bug
+        // in any of them should cost this document its diagnostics, not end the session.
+        let source = text.to_string();
+        match panic::catch_unwind(move || analyze(&source)) {
+            Ok(Ok((declarations, references, diagnostics))) => {
+                analysis.declarations = declarations;
+                analysis.references = references;
+                analysis.diagnostics = diagnostics
+                    .into_iter()
+                    .map(|(line, severity, message)| analysis.diagnostic(analysis.lines.line_range(line), severity, &message))
+                    .collect();
+            }
+            Ok(Err(errors)) => {
+                analysis.diagnostics = errors
+                    .into_iter()
+                    .map(|err| analysis.diagnostic(analysis.lines.range(err.span), SEVERITY_ERROR, &err.message))
+                    .collect();
+            }
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                    .unwrap_or_else(|| "Internal error.".to_string());
+                analysis.diagnostics = vec![analysis.diagnostic(analysis.lines.line_range(1), SEVERITY_ERROR, &message)];
+            }
+        }
+        analysis
+    }
+
+    fn diagnostic(&self, range: Value, severity: u32, message: &str) -> Value {
+        json!({ "range": range, "severity": severity, "source": "aoi", "message": message })
+    }
+
+    // The declaration named by the identifier under `offset`, if any.
+    fn declaration_at(&self, offset: usize) -> Option<(Span, &Declaration)> {
+        self.references
+            .iter()
+            .find(|(span, _)| span.start <= offset && offset <= span.end)
+            .map(|(span, index)| (*span, &self.declarations[*index]))
+    }
+
+    fn hover(&self, offset: usize) -> Value {
+        match self.declaration_at(offset) {
+            Some((span, decl)) => {
+                let mut value = format!("```aoi\n{}\n```", decl.detail);
+                if let Some(doc) = &decl.doc {
+                    value.push_str(&format!("\n\n{}", doc));
+                }
+                json!({
+                    "contents": { "kind": "markdown", "value": value },
+                    "range": self.lines.range(span)
+                })
+            }
+            None => Value::Null,
+        }
+    }
+
+    fn definition(&self, uri: &str, offset: usize) -> Value {
+        match self.declaration_at(offset) {
+            Some((_, decl)) => json!({ "uri": uri, "range": self.lines.range(decl.span) }),
+            None => Value::Null,
+        }
+    }
+
+    fn document_symbols(&self) -> Value {
+        Value::Array(self.symbols(None))
+    }
+
+    fn symbols(&self, parent: Option<usize>) -> Vec<Value> {
+        self.declarations
+            .iter()
+            .enumerate()
+            .filter(|(_, decl)| decl.parent == parent && decl.kind != DeclKind::Parameter)
+            .map(|(index, decl)| {
+                let full = Span {
+                    start: self.lines.starts[decl.line.saturating_sub(1)],
+                    end: decl.span.end,
+                };
+                let mut range = self.lines.range(full);
+                range["end"] = self.lines.line_range(decl.end_line)["end"].clone();
+                let (kind, children) = match decl.kind {
+                    DeclKind::Function => (SYMBOL_FUNCTION, self.symbols(Some(index))),
+                    _ => (SYMBOL_VARIABLE, Vec::new()),
+                };
+                json!({
+                    "name": decl.name,
+                    "detail": decl.detail,
+                    "kind": kind,
+                    "range": range,
+                    "selectionRange": self.lines.range(decl.span),
+                    "children": children
+                })
+            })
+            .collect()
+    }
+
+    fn completion(&self, offset: usize) -> Value {
+        let line = self.lines.position(offset)["line"].as_u64().unwrap_or(0) as usize + 1;
+        let mut items: Vec<Value> = Vec::new();
+        let mut seen: Vec<&str> = Vec::new();
+        // Innermost declarations come last, so walk backwards to let them shadow outer ones.
+        for decl in self.declarations.iter().rev() {
+            let in_scope = decl.scope.0 <= line && line <= decl.scope.1;
+            let declared = decl.kind == DeclKind::Function || decl.line <= line;
+            if !in_scope || !declared || seen.contains(&decl.name.as_str()) {
+                continue;
+            }
+            seen.push(&decl.name);
+            let kind = match decl.kind {
+                DeclKind::Function => COMPLETION_FUNCTION,
+                _ => COMPLETION_VARIABLE,
+            };
+            let mut item = json!({ "label": decl.name, "kind": kind, "detail": decl.detail });
+            if let Some(doc) = &decl.doc {
+                item["documentation"] = json!({ "kind": "markdown", "value": doc });
+            }
+            items.push(item);
+        }
+        for name in interpreter::builtin_names() {
+            if !seen.contains(&name) {
+                items.push(json!({ "label": name, "kind": COMPLETION_FUNCTION, "detail": "builtin" }));
+            }
+        }
+        items.extend(
+            KEYWORDS
+                .iter()
+                .map(|keyword| json!({ "label": keyword, "kind": COMPLETION_KEYWORD })),
+        );
+        Value::Array(items)
+    }
+}
+
+type Resolved = (Vec<Declaration>, Vec<(Span, usize)>, Vec<(usize, u32, String)>);
+
+// Syntax errors stop the analysis; otherwise returns declarations, references and
+// line-based diagnostics from the type checker and linter.
+fn analyze(source: &str) -> Result<Resolved, Vec<cst::SyntaxError>> {
+    let mut tokenizer = Tokensizer::new(source.to_string());
+    let tokens = tokenizer.tokenize();
+    let mut parser = Parser::new(tokens);
+    let Some(statements) = parser.parse() else {
+        // What the tokenizer rejects (stray characters, bad escapes, unterminated strings) is
+        // only printed; the lossless tree reports the same problems with their spans.
+        let mut errors = if tokenizer.had_error() {
+            cst::parse_with_errors(source).1
+        } else {
+            Vec::new()
+        };
+        if errors.is_empty() {
+            errors = parser
+                .errors()
+                .iter()
+                .map(|err| cst::SyntaxError {
+                    span: err.span,
+                    message: err.message.clone(),
+                })
+                .collect();
+        }
+        if errors.is_empty() {
+            errors.push(cst::SyntaxError {
+                span: Span::default(),
+                message: "Parsing failed due to syntax errors.".to_string(),
+            });
+        }
+        return Err(errors);
+    };
+
+    let mut diagnostics = Vec::new();
+    for err in typechecker::check(&statements) {
+        diagnostics.push((err.line, SEVERITY_ERROR, err.message));
+    }
+    if let Ok(warnings) = linter::lint_source(source, &LintConfig::default()) {
+        for warning in warnings {
+            diagnostics.push((warning.line, SEVERITY_WARNING, format!("{} [{}]", warning.message, warning.rule.name())));
+        }
+    }
+
+    let mut resolver = Resolver {
+        scopes: Vec::new(),
+        functions: Vec::new(),
+        declarations: Vec::new(),
+        references: Vec::new(),
+    };
+    resolver.scope(&statements, (1, usize::MAX));
+    Ok((resolver.declarations, resolver.references, diagnostics))
+}
+
+// Binds every identifier to its declaration, following the interpreter's scoping.
+struct Resolver {
+    scopes: Vec<((usize, usize), HashMap<String, usize>)>,
+    functions: Vec<usize>, // enclosing function declarations
+    declarations: Vec<Declaration>,
+    references: Vec<(Span, usize)>,
+}
+
+impl Resolver {
+    // Resolves `statements` in a new scope spanning `lines`.
+    fn scope(&mut self, statements: &[Stmt], lines: (usize, usize)) {
+        self.scopes.push((lines, HashMap::new()));
+        self.statements(statements);
+        self.scopes.pop();
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        // Function bodies run when called, so they see everything their scope declares,
+        // including names that come after them. Resolve them once the scope is complete.
+        let mut bodies = Vec::new();
+        for stmt in statements {
+            match stmt {
+                Stmt::Function { .. } => bodies.push((self.function_declaration(stmt), stmt)),
+                _ => self.statement(stmt),
+            }
+        }
+        for (index, stmt) in bodies {
+            self.function_body(index, stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expression { expression, .. } | Stmt::Print { expression, .. } => self.expression(expression),
+            Stmt::Var {
+                name,
+                type_annotation,
+                initializer,
+                line,
+                doc,
+                ..
+            } => {
+                if let Some(init) = initializer {
+                    self.expression(init);
+                }
+                let mut detail = format!("var {}", name.lexeme);
+                if let Some(ty) = type_annotation {
+                    detail.push_str(&format!(": {}", ty.lexeme));
+                }
+                let index = self.declare(name, DeclKind::Variable, *line, *line, detail);
+                self.declarations[index].doc = doc.clone();
+            }
+            Stmt::Block {
+                statements,
+                line,
+                end_line,
+                ..
+            } => self.scope(statements, (*line, *end_line)),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.expression(condition);
+                self.nested(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.nested(else_branch);
+                }
+            }
+            Stmt::While { condition, body, .. } => {
+                self.expression(condition);
+                self.nested(body);
+            }
+            Stmt::Input { name, .. } => self.reference(name),
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                line,
+                ..
+            } => {
+                let end_line = match body.as_ref() {
+                    Stmt::Block { end_line, .. } => *end_line,
+                    other => other.line(),
+                };
+                self.scopes.push(((*line, end_line), HashMap::new()));
+                if let Some(init) = initializer {
+                    self.statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.expression(condition);
+                }
+                if let Some(increment) = increment {
+                    self.expression(increment);
+                }
+                self.nested(body);
+                self.scopes.pop();
+            }
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+                line,
+                ..
+            } => {
+                self.expression(iterable);
+                let end_line = match body.as_ref() {
+                    Stmt::Block { end_line, .. } => *end_line,
+                    other => other.line(),
+                };
+                self.scopes.push(((*line, end_line), HashMap::new()));
+                let detail = format!("(loop variable) {}", name.lexeme);
+                self.declare(name, DeclKind::Variable, *line, *line, detail);
+                self.nested(body);
+                self.scopes.pop();
+            }
+            Stmt::Match { subject, arms, .. } => {
+                self.expression(subject);
+                for arm in arms {
+                    let end_line = match &arm.body {
+                        Stmt::Block { end_line, .. } => *end_line,
+                        other => other.line(),
+                    };
+                    self.scopes.push(((arm.line, end_line), HashMap::new()));
+                    match &arm.pattern {
+                        Pattern::Literal { value } => self.expression(value),
+                        Pattern::Range { start, end, .. } => {
+                            self.expression(start);
+                            self.expression(end);
+                        }
+                        Pattern::Binding { name } => {
+                            let detail = format!("(match binding) {}", name.lexeme);
+                            self.declare(name, DeclKind::Variable, arm.line, arm.line, detail);
+                        }
+                        Pattern::Wildcard { .. } => {}
+                    }
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.nested(&arm.body);
+                    self.scopes.pop();
+                }
+            }
+            Stmt::Function { .. } => {
+                let index = self.function_declaration(stmt);
+                self.function_body(index, stmt);
+            }
+            Stmt::Return { value, .. } | Stmt::Yield { value, .. } => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+        }
+    }
+
+    // A branch or loop body that is a lone function declaration still gets resolved.
+    fn nested(&mut self, stmt: &Stmt) {
+        self.statements(std::slice::from_ref(stmt));
+    }
+
+    fn function_declaration(&mut self, stmt: &Stmt) -> usize {
+        let Stmt::Function {
+            name,
+            params,
+            param_types,
+            return_type,
+            end_line,
+            doc,
+            ..
+        } = stmt
+        else {
+            unreachable!("function_declaration called on a non-function statement");
+        };
+        let params: Vec<String> = params
+            .iter()
+            .zip(param_types)
+            .map(|(param, ty)| annotated(param, ty))
+            .collect();
+        let mut detail = format!("fun {}({})", name.lexeme, params.join(", "));
+        if let Some(ty) = return_type {
+            detail.push_str(&format!(": {}", ty.lexeme));
+        }
+        let index = self.declare(name, DeclKind::Function, name.line, *end_line, detail);
+        self.declarations[index].doc = doc.clone();
+        index
+    }
+
+    fn function_body(&mut self, index: usize, stmt: &Stmt) {
+        let Stmt::Function {
+            name,
+            params,
+            param_types,
+            body,
+            end_line,
+            ..
+        } = stmt
+        else {
+            return;
+        };
+        self.functions.push(index);
+        self.scopes.push(((name.line, *end_line), HashMap::new()));
+        for (param, ty) in params.iter().zip(param_types) {
+            let detail = format!("(parameter) {}", annotated(param, ty));
+            self.declare(param, DeclKind::Parameter, param.line, param.line, detail);
+        }
+        self.statements(body);
+        self.scopes.pop();
+        self.functions.pop();
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal(_) => {}
+            Expr::Grouping(g) => self.expression(&g.expression),
+            Expr::Variable(v) => self.reference(&v.name),
+            Expr::Assign { name, value } => {
+                self.expression(value);
+                self.reference(name);
+            }
+            Expr::CompoundAssign { name, value, .. } => {
+                self.expression(value);
+                self.reference(name);
+            }
+            Expr::Increment { name, .. } => self.reference(name),
+            Expr::Unary(u) => self.expression(&u.right),
+            Expr::Binary(b) => {
+                self.expression(&b.left);
+                self.expression(&b.right);
+            }
+            Expr::Logical { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expr::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expression(condition);
+                self.expression(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.expression(else_branch);
+                }
+            }
+            Expr::Call { callee, arguments, .. } => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            Expr::Interpolation { parts } => {
+                for part in parts {
+                    self.expression(part);
+                }
+            }
+            Expr::Range { start, end, step, .. } => {
+                self.expression(start);
+                self.expression(end);
+                if let Some(step) = step {
+                    self.expression(step);
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Token, kind: DeclKind, line: usize, end_line: usize, detail: String) -> usize {
+        let index = self.declarations.len();
+        let scope = self.scopes.last_mut().expect("resolver always has a scope");
+        self.declarations.push(Declaration {
+            name: name.lexeme.clone(),
+            kind,
+            span: name.span,
+            line,
+            end_line,
+            scope: scope.0,
+            parent: self.functions.last().copied(),
+            detail,
+            doc: None,
+        });
+        scope.1.insert(name.lexeme.clone(), index);
+        // The name itself also leads to its declaration.
+        self.references.push((name.span, index));
+        index
+    }
+
+    fn reference(&mut self, name: &Token) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|(_, names)| names.get(&name.lexeme).copied());
+        if let Some(index) = found {
+            self.references.push((name.span, index));
+        }
+    }
+}
+
+fn annotated(param: &Token, ty: &Option<Token>) -> String {
+    match ty {
+        Some(ty) => format!("{}: {}", param.lexeme, ty.lexeme),
+        None => param.lexeme.clone(),
+    }
+}
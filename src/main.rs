@@ -1,37 +1,113 @@
-mod error;
-mod token;
-mod astprinter;
-mod expr;
-mod interpreter;
-mod parser;
-mod stmt;
-mod environment;
-
+use aoi::{
+    coverage, cst, dap, debugger, formatter, linter, lsp, parser, profiler, stmt, testrunner,
+    typechecker,
+};
 use std::env;
 use std::fs;
-use token::Tokensizer;
-use interpreter::Interpreter;
+use aoi::interpreter::{Hook, Interpreter};
+use aoi::token::{TokenLiteral, TokenType, Tokensizer};
+use std::cell::RefCell;
+use std::rc::Rc;
 
 use axum::{
     body::Bytes,
     http::StatusCode,
     response::IntoResponse,
     routing::post,
-    Router,
+    Json, Router,
 };
+use aoi::astprinter::AstPrinter;
+use serde_json::json;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use std::str::FromStr;
 
 async fn run_handler(bytes: Bytes) -> impl IntoResponse {
-    let code = String::from_utf8(bytes.to_vec()).unwrap();
+    let code = String::from_utf8_lossy(&bytes);
     let result = run_code(&code);
     (StatusCode::OK, result)
 }
 
+// Like `/run`, but answers with JSON holding the output and a profile of the run.
+async fn profile_handler(bytes: Bytes) -> impl IntoResponse {
+    let code = String::from_utf8_lossy(&bytes);
+    let profiler = Rc::new(RefCell::new(profiler::Profiler::new()));
+    let output = run_code_with_hook(&code, Some(profiler.clone()));
+    profiler.borrow_mut().finish();
+    let profile = profiler.borrow().to_json();
+    (StatusCode::OK, Json(json!({ "output": output, "profile": profile })))
+}
+
+async fn tokens_handler(bytes: Bytes) -> impl IntoResponse {
+    let code = String::from_utf8_lossy(&bytes);
+    (StatusCode::OK, Json(tokens_json(&code)))
+}
+
+async fn ast_handler(bytes: Bytes) -> impl IntoResponse {
+    let code = String::from_utf8_lossy(&bytes);
+    match ast_json(&code) {
+        Ok(ast) => (StatusCode::OK, Json(ast)),
+        Err(err) => (StatusCode::BAD_REQUEST, Json(json!({ "error": err }))),
+    }
+}
+
+fn tokens_json(source: &str) -> serde_json::Value {
+    let tokens = Tokensizer::new(source.to_string()).tokenize();
+    json!(tokens)
+}
+
+fn ast_json(source: &str) -> Result<serde_json::Value, String> {
+    let tokens = Tokensizer::new(source.to_string()).tokenize();
+    match parser::Parser::new(tokens).parse() {
+        Some(statements) => Ok(json!(statements)),
+        None => Err("Parsing failed due to syntax errors.".to_string()),
+    }
+}
+
+// `tokens [--json] <file>` and `ast [--json] <file>`: dump what the tokenizer and parser see.
+fn inspect_command(command: &str, args: &[String]) -> i32 {
+    let as_json = args.iter().any(|arg| arg == "--json");
+    let Some(filename) = args.iter().find(|arg| *arg != "--json") else {
+        eprintln!("Usage: {} [--json] <filename>", command);
+        return 2;
+    };
+    let source = fs::read_to_string(filename).expect("Failed to read file");
+
+    if command == "tokens" {
+        let tokens = Tokensizer::new(source).tokenize();
+        if as_json {
+            println!("{}", serde_json::to_string_pretty(&tokens).unwrap());
+        } else {
+            for token in &tokens {
+                println!("{}", token);
+            }
+        }
+        return 0;
+    }
+
+    let tokens = Tokensizer::new(source).tokenize();
+    let Some(statements) = parser::Parser::new(tokens).parse() else {
+        eprintln!("Parsing failed due to syntax errors.");
+        return 1;
+    };
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&statements).unwrap());
+    } else {
+        for stmt in &statements {
+            println!("{}", AstPrinter.print_stmt(stmt));
+        }
+    }
+    0
+}
+
 
 
 fn run_code(source: &str) -> String {
+    run_code_with_hook(source, None)
+}
+
+// `run_code`, with `hook` (profiler, coverage, ...) watching the interpreter.
+fn run_code_with_hook(source: &str, hook: Option<Rc<RefCell<dyn Hook>>>) -> String {
     // Disallow "scan" keyword
     if source.contains("scan") {
         return "Error: Usage of 'scan' keyword is not allowed.".to_string();
@@ -49,18 +125,312 @@ fn run_code(source: &str) -> String {
 
     let mut tokenizer = Tokensizer::new(source.to_string());
     let tokens = tokenizer.tokenize();
+    // The pattern above only sees runs of digits, so check literal values too: `0xFF`,
+    // `1_000` and `2e3` must not get around the limit.
+    for token in &tokens {
+        let too_large = match &token.literal {
+            Some(TokenLiteral::Integer(n)) => *n > 148,
+            Some(TokenLiteral::BigInteger(_)) => true,
+            Some(TokenLiteral::Number(n)) => *n > 148.0,
+            _ => false,
+        };
+        if token.token_type == TokenType::NUMBER && too_large {
+            return format!("Error: Numeric value '{}' exceeds the limit of 148.", token.lexeme);
+        }
+    }
     let mut parser = parser::Parser::new(tokens);
 
     match parser.parse() {
         Some(statements) => {
+            // Reject programs with type mismatches before running anything
+            let type_errors = typechecker::check(&statements);
+            if !type_errors.is_empty() {
+                return type_errors.iter().map(|err| format!("{}\n", err)).collect();
+            }
             let mut interpreter = Interpreter::new();
-            let result = interpreter.interpret(&statements);
-            result
+            if let Some(hook) = hook {
+                interpreter.set_hook(hook);
+            }
+            interpreter.interpret(&statements)
         }
         None => "Parsing failed due to syntax errors.".to_string(),
     }
 }
 
+// `fmt [--check] <files...>`: rewrites files in place, or with --check only reports them.
+fn fmt_command(args: &[String]) -> i32 {
+    let check = args.iter().any(|arg| arg == "--check");
+    let files: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+    if files.is_empty() {
+        eprintln!("Usage: fmt [--check] <files...>");
+        return 2;
+    }
+
+    let mut status = 0;
+    for file in files {
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("{}: {}", file, err);
+                status = 2;
+                continue;
+            }
+        };
+        let formatted = match formatter::format_source(&source) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                eprintln!("{}: {}", file, err);
+                status = 2;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("Would reformat: {}", file);
+            status = status.max(1);
+        } else if let Err(err) = fs::write(file, formatted) {
+            eprintln!("{}: {}", file, err);
+            status = 2;
+        }
+    }
+    status
+}
+
+// `lint [--allow <rule>]... <files...>`: prints warnings, exits 1 if there were any.
+fn lint_command(args: &[String]) -> i32 {
+    let mut config = linter::LintConfig::default();
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--allow" {
+            let name = args.next().map(String::as_str).unwrap_or("");
+            match linter::Rule::from_name(name) {
+                Some(rule) => config.disabled.push(rule),
+                None => {
+                    eprintln!("Unknown lint rule '{}'.", name);
+                    return 2;
+                }
+            }
+        } else {
+            files.push(arg);
+        }
+    }
+    if files.is_empty() {
+        eprintln!("Usage: lint [--allow <rule>]... <files...>");
+        return 2;
+    }
+
+    let mut status = 0;
+    for file in files {
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("{}: {}", file, err);
+                status = 2;
+                continue;
+            }
+        };
+        match linter::lint_source(&source, &config) {
+            Ok(warnings) => {
+                for warning in &warnings {
+                    println!("{}: {}", file, warning);
+                }
+                if !warnings.is_empty() {
+                    status = status.max(1);
+                }
+            }
+            Err(err) => {
+                eprintln!("{}: {}", file, err);
+                status = 2;
+            }
+        }
+    }
+    status
+}
+
+// `--profile [--folded <out>] <file>`: runs the file, then prints a profile to stderr.
+fn profile_command(args: &[String]) -> i32 {
+    let mut folded = None;
+    let mut file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--folded" {
+            folded = args.next();
+        } else {
+            file = Some(arg);
+        }
+    }
+    let Some(file) = file else {
+        eprintln!("Usage: --profile [--folded <output>] <filename>");
+        return 2;
+    };
+    let source = match fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("{}: {}", file, err);
+            return 2;
+        }
+    };
+
+    let profiler = Rc::new(RefCell::new(profiler::Profiler::new()));
+    let result = run_code_with_hook(&source, Some(profiler.clone()));
+    profiler.borrow_mut().finish();
+    println!("{}", result);
+
+    let profiler = profiler.borrow();
+    eprint!("{}", profiler.report(&source));
+    if let Some(path) = folded {
+        if let Err(err) = fs::write(path, profiler.folded()) {
+            eprintln!("{}: {}", path, err);
+            return 2;
+        }
+    }
+    0
+}
+
+// `test [paths...]`: runs the tests in every `*_test.aoi` under the paths (default `.`).
+fn test_command(args: &[String]) -> i32 {
+    let paths: Vec<std::path::PathBuf> = if args.is_empty() {
+        vec![".".into()]
+    } else {
+        args.iter().map(Into::into).collect()
+    };
+    let files = testrunner::discover(&paths);
+    if files.is_empty() {
+        println!("No *{} files found.", testrunner::FILE_SUFFIX);
+        return 0;
+    }
+
+    let mut summary = testrunner::Summary::default();
+    for file in &files {
+        testrunner::run_file(file, &mut summary);
+    }
+    println!();
+    println!(
+        "{} tests: {} passed, {} failed",
+        summary.passed + summary.failed,
+        summary.passed,
+        summary.failed
+    );
+    if summary.failed > 0 { 1 } else { 0 }
+}
+
+// Parses and type-checks a program for the tools that run it themselves, reporting
+// problems on stderr. Unlike `run_code`, `scan` and large numbers are allowed.
+fn load_program(source: &str) -> Option<Vec<stmt::Stmt>> {
+    let tokens = Tokensizer::new(source.to_string()).tokenize();
+    let Some(statements) = parser::Parser::new(tokens).parse() else {
+        eprintln!("Parsing failed due to syntax errors.");
+        return None;
+    };
+    let type_errors = typechecker::check(&statements);
+    if !type_errors.is_empty() {
+        for err in &type_errors {
+            eprintln!("{}", err);
+        }
+        return None;
+    }
+    Some(statements)
+}
+
+// `coverage [--lcov <out>] <file>`: runs the file (input comes from stdin), then prints
+// the annotated listing and a summary.
+fn coverage_command(args: &[String]) -> i32 {
+    let mut lcov = None;
+    let mut file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--lcov" {
+            lcov = args.next();
+        } else {
+            file = Some(arg);
+        }
+    }
+    let Some(file) = file else {
+        eprintln!("Usage: coverage [--lcov <output>] <filename>");
+        return 2;
+    };
+    let source = match fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("{}: {}", file, err);
+            return 2;
+        }
+    };
+    let Some(statements) = load_program(&source) else {
+        return 1;
+    };
+
+    let coverage = Rc::new(RefCell::new(coverage::Coverage::new(&statements)));
+    let mut interpreter = Interpreter::new();
+    interpreter.set_hook(coverage.clone());
+    print!("{}", interpreter.interpret(&statements));
+
+    let coverage = coverage.borrow();
+    println!();
+    print!("{}", coverage.listing(&source));
+    println!("{}", coverage.summary());
+    if let Some(path) = lcov {
+        if let Err(err) = fs::write(path, coverage.lcov(file)) {
+            eprintln!("{}: {}", path, err);
+            return 2;
+        }
+    }
+    0
+}
+
+// `debug [--break <line>]... <file>`: runs the file under the interactive debugger.
+fn debug_command(args: &[String]) -> i32 {
+    let mut breakpoints = Vec::new();
+    let mut file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--break" {
+            match args.next().and_then(|line| line.parse::<usize>().ok()) {
+                Some(line) => breakpoints.push(line),
+                None => {
+                    eprintln!("--break expects a line number.");
+                    return 2;
+                }
+            }
+        } else {
+            file = Some(arg);
+        }
+    }
+    let Some(file) = file else {
+        eprintln!("Usage: debug [--break <line>]... <filename>");
+        return 2;
+    };
+    let source = match fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("{}: {}", file, err);
+            return 2;
+        }
+    };
+
+    let Some(statements) = load_program(&source) else {
+        return 1;
+    };
+
+    let console = debugger::Console::new(std::io::stdin().lock(), std::io::stdout(), &source);
+    let mut session = debugger::Debugger::new(console);
+    session.breakpoints.extend(breakpoints);
+    // Without breakpoints there would be nothing to stop at, so start paused.
+    if session.breakpoints.is_empty() {
+        session.stop_on_entry();
+    }
+    let session = Rc::new(RefCell::new(session));
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_hook(session.clone());
+    interpreter.interpret(&statements);
+    session.borrow_mut().frontend.message("Program finished.");
+    0
+}
+
 #[tokio::main]
 async fn main() {
     let args: Vec<String> = env::args().collect();
@@ -70,13 +440,59 @@ async fn main() {
 
         let app = Router::new()
             .route("/", axum::routing::get(|| async { "Aoi interpreter server is running" }))
-            .route("/run", post(run_handler));
+            .route("/run", post(run_handler))
+            .route("/profile", post(profile_handler))
+            .route("/tokens", post(tokens_handler))
+            .route("/ast", post(ast_handler));
 
         // Read PORT from environment, default to 8080
         let port = env::var("PORT").unwrap_or("8080".to_string());
         let addr = SocketAddr::from_str(&format!("0.0.0.0:{}", port)).expect("Invalid PORT value");
         let listener = TcpListener::bind(addr).await.unwrap();
         axum::serve(listener, app).await.unwrap();
+    } else if args.len() >= 2 && args[1] == "lsp" {
+        // Language server speaking JSON-RPC on stdin/stdout
+        let stdin = std::io::stdin();
+        std::process::exit(lsp::serve(stdin.lock(), std::io::stdout().lock()));
+    } else if args.len() >= 2 && args[1] == "debug" {
+        std::process::exit(debug_command(&args[2..]));
+    } else if args.len() >= 2 && args[1] == "test" {
+        std::process::exit(test_command(&args[2..]));
+    } else if args.len() >= 2 && args[1] == "coverage" {
+        std::process::exit(coverage_command(&args[2..]));
+    } else if args.len() >= 2 && args[1] == "dap" {
+        // Debug Adapter Protocol on stdin/stdout
+        std::process::exit(dap::serve(std::io::stdin().lock(), std::io::stdout()));
+    } else if args.len() >= 2 && args[1] == "fmt" {
+        std::process::exit(fmt_command(&args[2..]));
+    } else if args.len() >= 3 && args[1] == "check" {
+        // Type-check without running
+        let source = fs::read_to_string(&args[2]).expect("Failed to read file");
+        let tokens = Tokensizer::new(source).tokenize();
+        let Some(statements) = parser::Parser::new(tokens).parse() else {
+            eprintln!("Parsing failed due to syntax errors.");
+            std::process::exit(1);
+        };
+        let type_errors = typechecker::check(&statements);
+        for err in &type_errors {
+            println!("{}", err);
+        }
+        std::process::exit(if type_errors.is_empty() { 0 } else { 1 });
+    } else if args.len() >= 2 && args[1] == "lint" {
+        std::process::exit(lint_command(&args[2..]));
+    } else if args.len() >= 2 && (args[1] == "tokens" || args[1] == "ast") {
+        std::process::exit(inspect_command(&args[1], &args[2..]));
+    } else if args.len() >= 3 && args[1] == "cst" {
+        // Dump the lossless syntax tree, trivia included
+        let source = fs::read_to_string(&args[2]).expect("Failed to read file");
+        let tree = cst::parse(&source);
+        print!("{}", tree.debug_tree());
+        if tree.text() != source {
+            eprintln!("Error: syntax tree does not reproduce the source.");
+            std::process::exit(1);
+        }
+    } else if args.len() >= 2 && args[1] == "--profile" {
+        std::process::exit(profile_command(&args[2..]));
     } else if args.len() >= 2 {
         // CLI mode
         let filename = &args[1];
@@ -87,7 +503,19 @@ async fn main() {
     } else {
         eprintln!("Usage:");
         eprintln!("  ./server <filename>       # CLI mode");
+        eprintln!("  ./server --profile [--folded <output>] <filename>  # Run and report where time went");
         eprintln!("  ./server server           # Start web server");
+        eprintln!("  ./server fmt [--check] <files...>  # Format source files");
+        eprintln!("  ./server cst <filename>   # Print the lossless syntax tree");
+        eprintln!("  ./server tokens [--json] <filename>  # Print the token stream");
+        eprintln!("  ./server ast [--json] <filename>     # Print the parsed program");
+        eprintln!("  ./server lint [--allow <rule>]... <files...>  # Report likely mistakes");
+        eprintln!("  ./server check <filename> # Type-check without running");
+        eprintln!("  ./server lsp              # Language server over stdio");
+        eprintln!("  ./server debug [--break <line>]... <filename>  # Interactive debugger");
+        eprintln!("  ./server dap              # Debug Adapter Protocol over stdio");
+        eprintln!("  ./server test [paths...]  # Run test_* functions in *_test.aoi files");
+        eprintln!("  ./server coverage [--lcov <output>] <filename>  # Report which lines ran");
     }
 }
 
@@ -1,39 +1,53 @@
 use crate::expr::Variable;
 use crate::expr::{Binary, Expr, Grouping, Literal, Unary};
-use crate::stmt::Stmt;
-use crate::token::{Token, TokenLiteral, TokenType};
-#[allow(dead_code)]
+use crate::stmt::{MatchArm, Pattern, Stmt};
+use crate::token::{Span, Token, TokenLiteral, TokenType};
 
+// How deeply brackets, blocks, bodies and prefix operators may nest. The parser and
+// everything that walks the tree recurse once per level, so this keeps hostile input
+// from overflowing the stack.
+pub(crate) const MAX_NESTING: usize = 100;
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    depth: usize,            // current nesting, see MAX_NESTING
+    had_error: bool,         // a statement failed to parse and was skipped
+    gave_up: bool,           // MAX_NESTING was hit and the rest of the input skipped
+    error_at: Option<usize>, // token the last error was reported at
+    errors: Vec<ParseError>, // everything reported, see `errors()`
 }
 
-#[derive(Debug)]
-struct ParseError {
-    message: String,
+/// A syntax error, at the token it was found at.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub line: usize,
+    pub span: Span,
+    location: String,
+    pub message: String,
 }
 
-impl ParseError {
-    fn new(message: &str) -> Self {
-        ParseError {
-            message: message.to_string(),
-        }
-    }
-}
 use std::fmt;
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.message)
+        write!(f, "Error{}: {}", self.location, self.message)
     }
 }
 #[allow(dead_code)]
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+        Parser {
+            tokens,
+            current: 0,
+            depth: 0,
+            had_error: false,
+            gave_up: false,
+            error_at: None,
+            errors: Vec::new(),
+        }
     }
 
     fn expression(&mut self) -> Result<Expr, ParseError> {
@@ -42,123 +56,282 @@ impl Parser {
     fn assignment(&mut self) -> Result<Expr, ParseError> {
         let expr = self.or()?; // This should be `or()`, not `equality()`
 
-        if self.match_tokens(&[TokenType::EQUAL]) {
-            let value = self.assignment()?; // Recursively parse the RHS
+        if self.match_tokens(&[
+            TokenType::EQUAL,
+            TokenType::PLUS_EQUAL,
+            TokenType::MINUS_EQUAL,
+            TokenType::STAR_EQUAL,
+            TokenType::SLASH_EQUAL,
+            TokenType::PERCENT_EQUAL,
+        ]) {
+            let equals = self.previous().clone();
+            let value = self.nested(Self::assignment)?; // Recursively parse the RHS
 
             if let Expr::Variable(var) = expr {
                 // Ensure LHS is a variable
-                return Ok(Expr::Assign(var.name.lexeme.clone(), Box::new(value)));
+                if equals.token_type != TokenType::EQUAL {
+                    return Ok(Expr::CompoundAssign {
+                        name: var.name,
+                        operator: equals,
+                        value: Box::new(value),
+                    });
+                }
+                return Ok(Expr::Assign {
+                    name: var.name,
+                    value: Box::new(value),
+                });
             }
 
-            return Err(ParseError::new("Invalid assignment target."));
+            return Err(self.error(&equals, "Invalid assignment target."));
         }
 
         Ok(expr)
     }
 
-    fn equality(&mut self) -> Expr {
-        let mut expr = self.comparison();
+    // The bitwise levels sit between `and` and equality as in C, so `x & 1 == 0` is
+    // `x & (1 == 0)`.
+    fn bit_or(&mut self) -> Expr {
+        self.binary_chain(&[TokenType::PIPE], Self::bit_xor)
+    }
 
-        while self.match_tokens(&[TokenType::BANG_EQUAL, TokenType::EQUAL_EQUAL]) {
-            let operator = self.previous().clone();
-            let right = self.comparison();
-            expr = Expr::Binary(Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            });
-        }
+    fn bit_xor(&mut self) -> Expr {
+        self.binary_chain(&[TokenType::CARET], Self::bit_and)
+    }
 
-        expr
+    fn bit_and(&mut self) -> Expr {
+        self.binary_chain(&[TokenType::AMPERSAND], Self::equality)
     }
 
-    fn comparison(&mut self) -> Expr {
-        let mut expr = self.term();
+    fn equality(&mut self) -> Expr {
+        self.binary_chain(&[TokenType::BANG_EQUAL, TokenType::EQUAL_EQUAL], Self::comparison)
+    }
 
-        while self.match_tokens(&[
-            TokenType::GREATER,
-            TokenType::GREATER_EQUAL,
-            TokenType::LESS,
-            TokenType::LESS_EQUAL,
-        ]) {
-            let operator = self.previous().clone();
-            let right = self.term();
-            expr = Expr::Binary(Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            });
+    fn comparison(&mut self) -> Expr {
+        self.binary_chain(
+            &[
+                TokenType::GREATER,
+                TokenType::GREATER_EQUAL,
+                TokenType::LESS,
+                TokenType::LESS_EQUAL,
+            ],
+            Self::range,
+        )
+    }
+
+    // `start..end` or `start..=end`, optionally followed by `step n`. Ranges don't chain.
+    fn range(&mut self) -> Expr {
+        let start = self.shift();
+        if !self.match_tokens(&[TokenType::DOT_DOT, TokenType::DOT_DOT_EQUAL]) {
+            return start;
+        }
+        let depth = self.depth;
+        if !self.deepen() {
+            return start;
+        }
+        let operator = self.previous().clone();
+        let end = self.shift();
+        // `step` is only a keyword here, so it stays usable as a name.
+        let step = if self.check(TokenType::IDENTIFIER) && self.peek().lexeme == "step" {
+            self.advance();
+            Some(Box::new(self.shift()))
+        } else {
+            None
+        };
+        self.depth = depth;
+        Expr::Range {
+            start: Box::new(start),
+            operator,
+            end: Box::new(end),
+            step,
         }
+    }
 
-        expr
+    fn shift(&mut self) -> Expr {
+        self.binary_chain(&[TokenType::LESS_LESS, TokenType::GREATER_GREATER], Self::term)
     }
 
     fn term(&mut self) -> Expr {
-        let mut expr = self.factor();
-
-        while self.match_tokens(&[TokenType::MINUS, TokenType::PLUS]) {
-            let operator = self.previous().clone();
-            let right = self.factor();
-            expr = Expr::Binary(Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            });
-        }
-
-        expr
+        self.binary_chain(&[TokenType::MINUS, TokenType::PLUS], Self::factor)
     }
 
     fn factor(&mut self) -> Expr {
-        let mut expr = self.unary();
-
-        while self.match_tokens(&[TokenType::SLASH, TokenType::STAR]) {
+        self.binary_chain(
+            &[
+                TokenType::SLASH,
+                TokenType::STAR,
+                TokenType::PERCENT,
+                TokenType::TILDE_SLASH,
+            ],
+            Self::unary,
+        )
+    }
+
+    // A left-associative chain of `operand (operator operand)*`.
+    fn binary_chain(&mut self, operators: &[TokenType], operand: fn(&mut Self) -> Expr) -> Expr {
+        let mut expr = operand(self);
+        let depth = self.depth;
+
+        while self.match_tokens(operators) {
+            if !self.deepen() {
+                break;
+            }
             let operator = self.previous().clone();
-            let right = self.unary();
+            let right = operand(self);
             expr = Expr::Binary(Binary {
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
             });
         }
+        self.depth = depth;
 
         expr
     }
 
     fn unary(&mut self) -> Expr {
-        if self.match_tokens(&[TokenType::BANG, TokenType::MINUS]) {
+        if self.match_tokens(&[TokenType::BANG, TokenType::MINUS, TokenType::TILDE]) {
             let operator = self.previous().clone();
-            let right = self.unary();
+            let right = self
+                .nested(|parser| Ok(parser.unary()))
+                .unwrap_or_else(|_| Expr::Literal(Literal::new(TokenLiteral::Null)));
             return Expr::Unary(Unary {
                 operator,
                 right: Box::new(right),
             });
         }
+        if self.match_tokens(&[TokenType::PLUS_PLUS, TokenType::MINUS_MINUS]) {
+            let operator = self.previous().clone();
+            let target = self
+                .nested(|parser| Ok(parser.unary()))
+                .unwrap_or_else(|_| Expr::Literal(Literal::new(TokenLiteral::Null)));
+            return self.increment(operator, target, true);
+        }
 
-        match self.primary() {
+        let operand = match self.primary() {
+            Ok(expr) if self.match_tokens(&[TokenType::PLUS_PLUS, TokenType::MINUS_MINUS]) => {
+                let operator = self.previous().clone();
+                self.increment(operator, expr, false)
+            }
             Ok(expr) => expr,
-            Err(_err) => {
-                // Handle the error appropriately, for example by returning a default expression
-                return Expr::Literal(Literal::new(TokenLiteral::Null));
+            Err(err) => {
+                // Keep going so later errors are reported too, but `parse` fails.
+                self.record(err);
+                Expr::Literal(Literal::new(TokenLiteral::Null))
+            }
+        };
+        self.power(operand)
+    }
+
+    // `**` binds tighter than a prefix operator on its left (`-2 ** 2` is -4) and is
+    // right-associative, so its right operand is a whole unary expression (`2 ** -1`).
+    fn power(&mut self, base: Expr) -> Expr {
+        if !self.match_tokens(&[TokenType::STAR_STAR]) {
+            return base;
+        }
+        let operator = self.previous().clone();
+        let exponent = self
+            .nested(|parser| Ok(parser.unary()))
+            .unwrap_or_else(|_| Expr::Literal(Literal::new(TokenLiteral::Null)));
+        Expr::Binary(Binary {
+            left: Box::new(base),
+            operator,
+            right: Box::new(exponent),
+        })
+    }
+
+    // `++x`, `x++`, `--x` or `x--`; only variables can be incremented.
+    fn increment(&mut self, operator: Token, target: Expr, prefix: bool) -> Expr {
+        if let Expr::Variable(var) = target {
+            return Expr::Increment {
+                name: var.name,
+                operator,
+                prefix,
+            };
+        }
+        let message = if operator.token_type == TokenType::PLUS_PLUS {
+            "Invalid increment target."
+        } else {
+            "Invalid decrement target."
+        };
+        let err = self.error(&operator, message);
+        self.record(err);
+        Expr::Literal(Literal::new(TokenLiteral::Null))
+    }
+
+    // A string with `${...}` in it, its INTERPOLATION part just consumed. Errors are
+    // recorded here and the rest of the string skipped, so one bad hole is reported once.
+    fn interpolation(&mut self) -> Result<Expr, ParseError> {
+        let mut parts = Vec::new();
+        loop {
+            let part = self.previous().clone();
+            let text = part.literal.unwrap_or(TokenLiteral::String(String::new()));
+            parts.push(Expr::Literal(Literal::with_span(text, part.span)));
+            if part.token_type == TokenType::INTERPOLATION_END {
+                return Ok(Expr::Interpolation { parts });
+            }
+            // A part starting with `}` closes the hole (a nested string starts with a quote).
+            let closing = matches!(
+                self.peek().token_type,
+                TokenType::INTERPOLATION | TokenType::INTERPOLATION_END
+            ) && self.peek().lexeme.starts_with('}');
+            if closing {
+                let err = self.error(self.peek(), "Expect expression inside '${}'.");
+                self.record(err);
+                let span = self.peek().span;
+                parts.push(Expr::Literal(Literal::with_span(TokenLiteral::Null, span)));
+            } else {
+                parts.push(self.nested(Self::expression)?);
+            }
+            if self.match_tokens(&[TokenType::INTERPOLATION, TokenType::INTERPOLATION_END]) {
+                continue;
+            }
+            let err = self.error(self.peek(), "Expect '}' after interpolated expression.");
+            self.record(err);
+            self.skip_interpolation();
+            return Ok(Expr::Interpolation { parts });
+        }
+    }
+
+    // Skips past the INTERPOLATION_END that closes the string being parsed.
+    fn skip_interpolation(&mut self) {
+        let mut open = 1;
+        while open > 0 && !self.is_at_end() {
+            match self.advance().token_type {
+                TokenType::INTERPOLATION => open += 1,
+                TokenType::INTERPOLATION_END => open -= 1,
+                _ => {}
             }
         }
     }
 
     fn primary(&mut self) -> Result<Expr, ParseError> {
         if self.match_tokens(&[TokenType::FALSE]) {
-            return Ok(Expr::Literal(Literal::new(TokenLiteral::Boolean(false))));
+            let span = self.previous().span;
+            return Ok(Expr::Literal(Literal::with_span(TokenLiteral::Boolean(false), span)));
         }
         if self.match_tokens(&[TokenType::TRUE]) {
-            return Ok(Expr::Literal(Literal::new(TokenLiteral::Boolean(true))));
+            let span = self.previous().span;
+            return Ok(Expr::Literal(Literal::with_span(TokenLiteral::Boolean(true), span)));
         }
         if self.match_tokens(&[TokenType::NIL]) {
-            return Ok(Expr::Literal(Literal::new(TokenLiteral::Null)));
+            let span = self.previous().span;
+            return Ok(Expr::Literal(Literal::with_span(TokenLiteral::Null, span)));
+        }
+        // The tokenizer has already reported what is wrong with this string.
+        if self.match_tokens(&[TokenType::ERROR]) {
+            self.had_error = true;
+            let span = self.previous().span;
+            return Ok(Expr::Literal(Literal::with_span(TokenLiteral::Null, span)));
         }
         if self.match_tokens(&[TokenType::NUMBER, TokenType::STRING]) {
+            let span = self.previous().span;
             if let Some(value) = self.previous().literal.clone() {
-                return Ok(Expr::Literal(Literal::new(value)));
+                return Ok(Expr::Literal(Literal::with_span(value, span)));
             }
-            return Ok(Expr::Literal(Literal::new(TokenLiteral::Null)));
+            return Ok(Expr::Literal(Literal::with_span(TokenLiteral::Null, span)));
+        }
+        if self.match_tokens(&[TokenType::INTERPOLATION]) {
+            return self.interpolation();
         }
     
         //  Handle identifiers (variables or function calls)
@@ -168,26 +341,33 @@ impl Parser {
             });
     
             //  If followed by '(', parse it as a function call
+            let depth = self.depth;
             while self.match_tokens(&[TokenType::LEFT_PAREN]) {
+                if !self.deepen() {
+                    break;
+                }
                 expr = self.parse_call(expr)?;
             }
+            self.depth = depth;
     
             return Ok(expr);
         }
     
         //  Handle grouping (parentheses)
         if self.match_tokens(&[TokenType::LEFT_PAREN]) {
-            let expr = self.expression()?;
-            self.consume(TokenType::RIGHT_PAREN, "Expect ')' after expression.");
+            let start = self.previous().span.start;
+            let expr = self.nested(Self::expression)?;
+            let end = self
+                .consume(TokenType::RIGHT_PAREN, "Expect ')' after expression.")?
+                .span
+                .end;
             return Ok(Expr::Grouping(Grouping {
                 expression: Box::new(expr),
+                span: Span { start, end },
             }));
         }
     
-        Err(ParseError::new(&format!(
-            "Error at '{}': Expect expression.",
-            self.peek().lexeme
-        )))
+        Err(self.error(self.peek(), "Expect expression."))
     }
     
 
@@ -200,12 +380,12 @@ impl Parser {
         }
         false
     }
-    fn consume(&mut self, token_type: TokenType, message: &str) -> Token {
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, ParseError> {
         if self.check(token_type) {
-            return self.advance().clone();
+            return Ok(self.advance().clone());
         }
 
-        panic!("{}", message); // Replace with proper error handling later
+        Err(self.error(self.peek(), message))
     }
 
     pub fn check(&self, token_type: TokenType) -> bool {
@@ -215,6 +395,13 @@ impl Parser {
         self.peek().token_type == token_type
     }
 
+    // Like `check`, for the token after the current one.
+    fn check_next(&self, token_type: TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|token| token.token_type == token_type)
+    }
+
     pub fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
             self.current += 1;
@@ -231,16 +418,67 @@ impl Parser {
     }
 
     pub fn previous(&self) -> &Token {
-        &self.tokens[self.current - 1]
+        &self.tokens[self.current.saturating_sub(1)]
     }
 
     fn error(&self, token: &Token, message: &str) -> ParseError {
-        ParseError::new(&format!(" Error at '{}': {}", token.lexeme, message))
+        let location = if token.token_type == TokenType::EOF {
+            " at end".to_string()
+        } else {
+            format!(" at '{}'", token.lexeme)
+        };
+        ParseError {
+            line: token.line,
+            span: token.span,
+            location,
+            message: message.to_string(),
+        }
     }
     fn report(&self, line: usize, location: &str, message: &str) {
         eprintln!("[line {}] Error{}: {}", line, location, message);
     }
 
+    // Reports an error that was recovered from; `parse` will still return None.
+    // Only the first error at a token is reported: a missing operand makes the statement
+    // around it fail at the same place.
+    fn record(&mut self, err: ParseError) {
+        let repeated = self.error_at == Some(self.current);
+        self.error_at = Some(self.current);
+        if !self.gave_up && !repeated {
+            self.report(err.line, &err.location, &err.message);
+            self.errors.push(err);
+        }
+        self.had_error = true;
+    }
+
+    // Runs `parse` one nesting level deeper, failing instead once MAX_NESTING is reached.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if !self.deepen() {
+            return Err(self.error(self.peek(), "Too deeply nested."));
+        }
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    // Goes one nesting level deeper. Chains like `a + b + c` or `f()()` call this once per
+    // operator, since each one nests the tree a level further, and restore `depth` after.
+    fn deepen(&mut self) -> bool {
+        if self.depth >= MAX_NESTING {
+            // Report once and skip to the end; whatever follows would only add noise.
+            let err = self.error(self.peek(), "Too deeply nested.");
+            self.record(err);
+            self.gave_up = true;
+            self.current = self.tokens.len() - 1;
+            return false;
+        }
+        self.depth += 1;
+        true
+    }
+
     fn synchronize(&mut self) {
         self.advance();
 
@@ -257,7 +495,9 @@ impl Parser {
                 | TokenType::IF
                 | TokenType::WHILE
                 | TokenType::PRINT
-                | TokenType::RETURN => return,
+                | TokenType::RETURN
+                | TokenType::YIELD
+                | TokenType::MATCH => return,
 
                 _ => {}
             }
@@ -265,114 +505,135 @@ impl Parser {
             self.advance();
         }
     }
+    /// The syntax errors reported so far, in the order they were found. Empty when `parse`
+    /// failed only because of errors the tokenizer reported.
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
     pub fn parse(&mut self) -> Option<Vec<Stmt>> {
         let mut statements = Vec::new();
         while !self.is_at_end() {
             if let Some(stmt) = self.statement() {
                 statements.push(stmt);
-            } else {
-                return None; // Return None if any statement fails
             }
         }
+        if self.had_error {
+            return None; // Return None if any statement fails
+        }
         Some(statements) // Return Some if parsing succeeds
     }
 
+    // Parses one statement. On a syntax error it reports it, skips to the next statement
+    // and returns None; `parse` then fails as a whole.
     fn statement(&mut self) -> Option<Stmt> {
+        match self.try_statement() {
+            Ok(stmt) => Some(stmt),
+            Err(err) => {
+                self.record(err);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, ParseError> {
         if self.match_tokens(&[TokenType::VAR]) {
-            return Some(self.variable_declaration());
+            return self.variable_declaration();
         }
         if self.match_tokens(&[TokenType::PRINT]) {
-            return Some(self.print_statement());
+            return self.print_statement();
         }
         if self.match_tokens(&[TokenType::LEFT_BRACE]) {
-            return Some(Stmt::Block(self.block())); //  NEW: Handle block statements
+            return self.nested(Self::block_statement); //  NEW: Handle block statements
         }
         if self.match_tokens(&[TokenType::IF]) {
-            return Some(self.if_statement());
+            return self.if_statement();
         }
         if self.match_tokens(&[TokenType::SCAN]) {
-            self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'scan'.");
-            let name = self.consume(TokenType::IDENTIFIER, "Expect variable name after 'scan'.");
-            self.consume(TokenType::RIGHT_PAREN, "Expect ')' after variable name.");
-            self.consume(TokenType::SEMICOLON, "Expect ';' after 'scan' statement.");
-            return Some(Stmt::Input { name });
+            let start = self.previous().span.start;
+            self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'scan'.")?;
+            let name = self.consume(TokenType::IDENTIFIER, "Expect variable name after 'scan'.")?;
+            self.consume(TokenType::RIGHT_PAREN, "Expect ')' after variable name.")?;
+            self.consume(TokenType::SEMICOLON, "Expect ';' after 'scan' statement.")?;
+            return Ok(Stmt::Input {
+                name,
+                span: self.span_from(start),
+            });
         }
-
         if self.match_tokens(&[TokenType::WHILE]) {
-            return match self.while_statement() {
-                Ok(stmt) => Some(stmt),
-                Err(_err) => {
-                    self.synchronize();
-                    None
-                }
-            };
+            return self.while_statement();
         }
-    if self.match_tokens(&[TokenType::FUN]) {
-        return match self.function() {
-            Ok(stmt) => Some(stmt),
-            Err(_err) => {
-                self.synchronize();
-                None
-            }
-        };
-    }
-    if self.match_tokens(&[TokenType::RETURN]) {
-        return Some(self.return_statement());
-    }
-    if self.match_tokens(&[TokenType::FOR]) {
-            return match self.for_statement() {
-                Ok(stmt) => Some(stmt),
-                Err(_err) => {
-                    self.synchronize();
-                    None
-                }
-            };
-        
-    
+        if self.match_tokens(&[TokenType::FUN]) {
+            return self.function();
+        }
+        if self.match_tokens(&[TokenType::RETURN]) {
+            return self.return_statement();
+        }
+        if self.match_tokens(&[TokenType::FOR]) {
+            return self.for_statement();
+        }
+        if self.match_tokens(&[TokenType::YIELD]) {
+            return self.yield_statement();
+        }
+        if self.match_tokens(&[TokenType::MATCH]) {
+            return self.match_statement();
         }
         self.expression_statement()
     }
 
-    fn print_statement(&mut self) -> Stmt {
-        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'print'."); // Require '('
+    // From byte `start` through the token just consumed, for a statement's `span`.
+    fn span_from(&self, start: usize) -> Span {
+        Span {
+            start,
+            end: self.previous().span.end,
+        }
+    }
 
-        let value = match self.expression() {
-            Ok(expr) => expr,
-            Err(_) => {
-                self.synchronize();
-                return Stmt::Print {
-                    expression: Expr::Literal(Literal::new(TokenLiteral::Null)),
-                };
-            }
-        };
+    // The body of an `if`, `while` or `for`.
+    fn body(&mut self, message: &str) -> Result<Stmt, ParseError> {
+        if self.is_at_end() {
+            return Err(self.error(self.peek(), message));
+        }
+        self.nested(Self::try_statement)
+    }
 
-        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after expression."); // Require ')'
-        self.consume(TokenType::SEMICOLON, "Expect ';' after print statement."); // Require ';'
+    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let line = self.previous().line;
+        let start = self.previous().span.start;
+        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'print'.")?; // Require '('
+        let value = self.expression()?;
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after expression.")?; // Require ')'
+        self.consume(TokenType::SEMICOLON, "Expect ';' after print statement.")?; // Require ';'
 
-        Stmt::Print { expression: value }
+        Ok(Stmt::Print {
+            expression: value,
+            line,
+            span: self.span_from(start),
+        })
     }
 
-    fn expression_statement(&mut self) -> Option<Stmt> {
-        let expr = match self.expression() {
-            Ok(expr) => expr,
-            Err(_err) => {
-                self.synchronize();
-                return None;
-            }
-        };
-        self.consume(TokenType::SEMICOLON, "Expect ';' after expression.");
-        Some(Stmt::Expression { expression: expr })
+    fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let line = self.peek().line;
+        let start = self.peek().span.start;
+        let expr = self.expression()?;
+        self.consume(TokenType::SEMICOLON, "Expect ';' after expression.")?;
+        Ok(Stmt::Expression {
+            expression: expr,
+            line,
+            span: self.span_from(start),
+        })
     }
 
-    fn variable_declaration(&mut self) -> Stmt {
-        let name_token = self.consume(TokenType::IDENTIFIER, "Expect variable name.");
-        let name = name_token.lexeme.clone();
+    fn variable_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let line = self.previous().line;
+        let start = self.previous().span.start;
+        let doc = self.previous().doc.clone();
+        let name = self.consume(TokenType::IDENTIFIER, "Expect variable name.")?;
+        let type_annotation = self.type_annotation()?;
 
         let initializer = if self.match_tokens(&[TokenType::EQUAL]) {
-            match self.expression() {
-                Ok(expr) => Some(expr),
-                Err(_) => None,
-            }
+            Some(self.expression()?)
         } else {
             None
         };
@@ -380,12 +641,31 @@ impl Parser {
         self.consume(
             TokenType::SEMICOLON,
             "Expect ';' after variable declaration.",
-        );
+        )?;
+
+        Ok(Stmt::Var {
+            name,
+            type_annotation,
+            initializer,
+            line,
+            doc,
+            span: self.span_from(start),
+        })
+    }
 
-        Stmt::Var { name, initializer }
+    // Optional `: type` after a variable, parameter or parameter list.
+    fn type_annotation(&mut self) -> Result<Option<Token>, ParseError> {
+        if !self.match_tokens(&[TokenType::COLON]) {
+            return Ok(None);
+        }
+        // `nil` and `fun` are keywords, but also valid type names.
+        if self.match_tokens(&[TokenType::IDENTIFIER, TokenType::NIL, TokenType::FUN]) {
+            return Ok(Some(self.previous().clone()));
+        }
+        Ok(Some(self.consume(TokenType::IDENTIFIER, "Expect type name after ':'.")?))
     }
 
-    fn block(&mut self) -> Vec<Stmt> {
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
         let mut statements = Vec::new();
 
         while !self.check(TokenType::RIGHT_BRACE) && !self.is_at_end() {
@@ -394,47 +674,150 @@ impl Parser {
             }
         }
 
-        self.consume(TokenType::RIGHT_BRACE, "Expect '}' after block.");
-        statements
+        self.consume(TokenType::RIGHT_BRACE, "Expect '}' after block.")?;
+        Ok(statements)
     }
 
-    fn if_statement(&mut self) -> Stmt {
-        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'if'.");
-        let condition = self.expression().unwrap();
-        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after if condition.");
+    // Parses the rest of a `{ ... }` block whose opening brace was just matched.
+    fn block_statement(&mut self) -> Result<Stmt, ParseError> {
+        let line = self.previous().line;
+        let start = self.previous().span.start;
+        let statements = self.block()?;
+        Ok(Stmt::Block {
+            statements,
+            line,
+            end_line: self.previous().line,
+            span: self.span_from(start),
+        })
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        let line = self.previous().line;
+        let start = self.previous().span.start;
+        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after if condition.")?;
 
-        let then_branch = Box::new(self.statement().unwrap());
+        let then_branch = Box::new(self.body("Expect statement after if condition.")?);
         let else_branch = if self.match_tokens(&[TokenType::ELSE]) {
-            Some(Box::new(self.statement().unwrap()))
+            Some(Box::new(self.body("Expect statement after 'else'.")?))
         } else {
             None
         };
 
-        Stmt::If {
+        Ok(Stmt::If {
             condition,
             then_branch,
             else_branch,
+            line,
+            span: self.span_from(start),
+        })
+    }
+    fn match_statement(&mut self) -> Result<Stmt, ParseError> {
+        let line = self.previous().line;
+        let start = self.previous().span.start;
+        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'match'.")?;
+        let subject = self.expression()?;
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after match value.")?;
+        self.consume(TokenType::LEFT_BRACE, "Expect '{' before match arms.")?;
+        let mut arms = Vec::new();
+        while !self.check(TokenType::RIGHT_BRACE) && !self.is_at_end() {
+            let line = self.peek().line;
+            let pattern = self.pattern()?;
+            let guard = if self.match_tokens(&[TokenType::IF]) {
+                Some(self.expression()?)
+            } else {
+                None
+            };
+            self.consume(TokenType::EQUAL_GREATER, "Expect '=>' after match pattern.")?;
+            let body = self.body("Expect statement after '=>'.")?;
+            arms.push(MatchArm {
+                line,
+                pattern,
+                guard,
+                body,
+            });
+        }
+        self.consume(TokenType::RIGHT_BRACE, "Expect '}' after match arms.")?;
+        Ok(Stmt::Match {
+            subject,
+            arms,
+            line,
+            end_line: self.previous().line,
+            span: self.span_from(start),
+        })
+    }
+
+    // `_`, a name to bind, a literal, or a range between two number literals.
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        if self.match_tokens(&[TokenType::IDENTIFIER]) {
+            let name = self.previous().clone();
+            if name.lexeme == "_" {
+                return Ok(Pattern::Wildcard { token: name });
+            }
+            return Ok(Pattern::Binding { name });
+        }
+        let value = self.literal_pattern()?;
+        if self.match_tokens(&[TokenType::DOT_DOT, TokenType::DOT_DOT_EQUAL]) {
+            let operator = self.previous().clone();
+            let end = self.literal_pattern()?;
+            return Ok(Pattern::Range {
+                start: Box::new(value),
+                operator,
+                end: Box::new(end),
+            });
+        }
+        Ok(Pattern::Literal { value })
+    }
+
+    fn literal_pattern(&mut self) -> Result<Expr, ParseError> {
+        if self.check(TokenType::MINUS) && self.check_next(TokenType::NUMBER) {
+            let operator = self.advance().clone();
+            let right = self.primary()?;
+            return Ok(Expr::Unary(Unary {
+                operator,
+                right: Box::new(right),
+            }));
+        }
+        match self.peek().token_type {
+            TokenType::NUMBER
+            | TokenType::STRING
+            | TokenType::TRUE
+            | TokenType::FALSE
+            | TokenType::NIL
+            | TokenType::ERROR => self.primary(),
+            _ => Err(self.error(self.peek(), "Expect pattern.")),
         }
     }
+
     fn and(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.equality(); // Parse left-hand side
+        let mut expr = self.bit_or(); // Parse left-hand side
+        let depth = self.depth;
 
         while self.match_tokens(&[TokenType::AND]) {
+            if !self.deepen() {
+                break;
+            }
             let operator = self.previous().clone();
-            let right = self.equality(); // Parse right-hand side
+            let right = self.bit_or(); // Parse right-hand side
             expr = Expr::Logical {
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
             };
         }
+        self.depth = depth;
 
         Ok(expr)
     }
     fn or(&mut self) -> Result<Expr, ParseError> {
         let mut expr = self.and()?; // Parse left-hand side
+        let depth = self.depth;
 
         while self.match_tokens(&[TokenType::OR]) {
+            if !self.deepen() {
+                break;
+            }
             let operator = self.previous().clone();
             let right = self.and()?; // Parse right-hand side
             expr = Expr::Logical {
@@ -443,34 +826,47 @@ impl Parser {
                 right: Box::new(right),
             };
         }
+        self.depth = depth;
 
         Ok(expr)
     }
     fn while_statement(&mut self) -> Result<Stmt, ParseError> {
-        self.consume(TokenType::LEFT_PAREN, "Expected '(' after 'while'.");
+        let line = self.previous().line;
+        let start = self.previous().span.start;
+        self.consume(TokenType::LEFT_PAREN, "Expected '(' after 'while'.")?;
         let condition = self.expression()?; // Parse condition
-        self.consume(TokenType::RIGHT_PAREN, "Expected ')' after condition.");
-        let body = match self.statement() {
-            Some(stmt) => stmt,
-            None => return Err(ParseError::new("Expected statement for while body")),
-        }; // Parse loop body
+        self.consume(TokenType::RIGHT_PAREN, "Expected ')' after condition.")?;
+        let body = self.body("Expected statement for while body.")?; // Parse loop body
 
         Ok(Stmt::While {
             condition,
             body: Box::new(body),
+            line,
+            span: self.span_from(start),
         })
     }
     fn for_statement(&mut self) -> Result<Stmt, ParseError> {
-        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'for'.");
+        let line = self.previous().line;
+        let start = self.previous().span.start;
+        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'for'.")?;
+        if self.check(TokenType::IDENTIFIER) && self.check_next(TokenType::IN) {
+            return self.for_in_statement(line, start);
+        }
 
         // 🔹 Parse the initializer (`var i = 0;`)
         let initializer = if self.match_tokens(&[TokenType::SEMICOLON]) {
             None
         } else if self.match_tokens(&[TokenType::VAR]) {
-            Some(Box::new(self.variable_declaration()))
+            Some(Box::new(self.variable_declaration()?))
         } else {
+            let line = self.peek().line;
+            let start = self.peek().span.start;
+            let expression = self.expression()?;
+            self.consume(TokenType::SEMICOLON, "Expect ';' after loop initializer.")?;
             Some(Box::new(Stmt::Expression {
-                expression: self.expression()?,
+                expression,
+                line,
+                span: self.span_from(start),
             }))
         };
 
@@ -480,7 +876,7 @@ impl Parser {
         } else {
             None
         };
-        self.consume(TokenType::SEMICOLON, "Expect ';' after loop condition.");
+        self.consume(TokenType::SEMICOLON, "Expect ';' after loop condition.")?;
 
         // 🔹 Parse the increment (`i = i + 1`)
         let increment = if !self.check(TokenType::RIGHT_PAREN) {
@@ -488,55 +884,73 @@ impl Parser {
         } else {
             None
         };
-        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after for clauses.");
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after for clauses.")?;
 
         // 🔹 Parse the loop body (`{ write(i); }`)
-        let mut body = match self.statement() {
-            Some(stmt) => stmt,
-            None => return Err(ParseError::new("Expected statement for loop body")),
-        };
-
-        //  Append the increment to the end of the loop
-        if let Some(inc) = increment {
-            body = Stmt::Block(vec![body, Stmt::Expression { expression: inc }]);
-        }
+        let body = self.body("Expected statement for loop body.")?;
 
-        //  Convert into `while (condition) { body }`
-        let while_loop = Stmt::While {
-            condition: condition
-                .unwrap_or(Expr::Literal(Literal::new(TokenLiteral::Boolean(true)))), // Default: Always true
+        //  Keep the loop as written; the interpreter scopes the initializer to the loop
+        Ok(Stmt::For {
+            initializer,
+            condition,
+            increment,
             body: Box::new(body),
-        };
-
-        //  Wrap everything in a block: `{ var i = 0; while (i < 5) { body; i = i + 1; } }`
-        if let Some(init) = initializer {
-            return Ok(Stmt::Block(vec![*init, while_loop]));
-        }
-
-        Ok(while_loop)
+            line,
+            span: self.span_from(start),
+        })
+    }
+    // `for (name in iterable) body`, the `(` already consumed.
+    fn for_in_statement(&mut self, line: usize, start: usize) -> Result<Stmt, ParseError> {
+        let name = self.advance().clone();
+        self.advance(); // `in`
+        let iterable = self.expression()?;
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after for-in clause.")?;
+        let body = self.body("Expected statement for loop body.")?;
+        Ok(Stmt::ForIn {
+            name,
+            iterable,
+            body: Box::new(body),
+            line,
+            span: self.span_from(start),
+        })
     }
+
     fn function(&mut self) -> Result<Stmt, ParseError> {
-        let name = self.consume(TokenType::IDENTIFIER, "Expect function name.").clone();
-        self.consume(TokenType::LEFT_PAREN, "Expect '(' after function name.");
+        let doc = self.previous().doc.clone();
+        let start = self.previous().span.start;
+        let name = self.consume(TokenType::IDENTIFIER, "Expect function name.")?;
+        self.consume(TokenType::LEFT_PAREN, "Expect '(' after function name.")?;
     
         let mut params = Vec::new();
+        let mut param_types = Vec::new();
         if !self.check(TokenType::RIGHT_PAREN) {
             loop {
-                params.push(self.consume(TokenType::IDENTIFIER, "Expect parameter name.").clone());
+                params.push(self.consume(TokenType::IDENTIFIER, "Expect parameter name.")?);
+                param_types.push(self.type_annotation()?);
                 if !self.match_single(&TokenType::COMMA) {
                     break;
                 }
             }
         }
-        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after parameters.");
-        self.consume(TokenType::LEFT_BRACE, "Expect '{' before function body.");
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after parameters.")?;
+        let return_type = self.type_annotation()?;
+        self.consume(TokenType::LEFT_BRACE, "Expect '{' before function body.")?;
     
-        let body = self.block(); // Assume `block()` parses a block of statements
-        Ok(Stmt::Function { name, params, body })
+        let body = self.nested(Self::block)?; // Assume `block()` parses a block of statements
+        Ok(Stmt::Function {
+            name,
+            params,
+            param_types,
+            return_type,
+            body,
+            end_line: self.previous().line,
+            doc,
+            span: self.span_from(start),
+        })
     }
     
     fn match_single(&mut self, token_type: &TokenType) -> bool {
-        if self.check(token_type.clone()) {
+        if self.check(*token_type) {
             self.advance();
             true
         } else {
@@ -549,7 +963,7 @@ impl Parser {
     
         if !self.check(TokenType::RIGHT_PAREN) {
             loop {
-                match self.expression() {
+                match self.nested(Self::expression) {
                     Ok(expr) => arguments.push(expr),
                     Err(e) => return Err(e),
                 }
@@ -560,23 +974,37 @@ impl Parser {
             }
         }
     
-        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after arguments.");
+        let paren = self.consume(TokenType::RIGHT_PAREN, "Expect ')' after arguments.")?;
         
         Ok(Expr::Call {
             callee: Box::new(callee),
             arguments,
+            paren,
         })
     }
 
-    fn return_statement(&mut self) -> Stmt {
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let value = if !self.check(TokenType::SEMICOLON) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.consume(TokenType::SEMICOLON, "Expect ';' after return value.")?;
+        let span = self.span_from(keyword.span.start);
+        Ok(Stmt::Return { keyword, value, span })
+    }
+
+    fn yield_statement(&mut self) -> Result<Stmt, ParseError> {
         let keyword = self.previous().clone();
         let value = if !self.check(TokenType::SEMICOLON) {
-            Some(self.expression().unwrap())
+            Some(self.expression()?)
         } else {
             None
         };
-        self.consume(TokenType::SEMICOLON, "Expect ';' after return value.");
-        Stmt::Return { keyword, value }
+        self.consume(TokenType::SEMICOLON, "Expect ';' after yield value.")?;
+        let span = self.span_from(keyword.span.start);
+        Ok(Stmt::Yield { keyword, value, span })
     }
     
 }
@@ -0,0 +1,190 @@
+// Profiler built on `interpreter::Hook`: call counts and inclusive/exclusive time per
+// function, time and hits per source line, and folded stacks for flamegraph tools.
+use crate::interpreter::{Frame, Hook};
+use crate::stmt::Stmt;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+#[derive(Default, Clone, Copy)]
+struct FunctionStats {
+    calls: usize,
+    inclusive: Duration,
+    exclusive: Duration,
+}
+
+#[derive(Default, Clone, Copy)]
+struct LineStats {
+    hits: usize,
+    time: Duration, // from the start of a statement on this line to the next statement
+}
+
+struct Activation {
+    function: String,
+    started: Instant,
+    children: Duration, // time spent in calls made from this activation
+}
+
+#[derive(Default)]
+pub struct Profiler {
+    functions: HashMap<String, FunctionStats>,
+    lines: HashMap<usize, LineStats>,
+    folded: HashMap<String, Duration>, // `a;b;c` -> exclusive time of c under that stack
+    stack: Vec<Activation>,
+    current_line: Option<(usize, Instant)>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler {
+            functions: HashMap::new(),
+            lines: HashMap::new(),
+            folded: HashMap::new(),
+            stack: Vec::new(),
+            current_line: None,
+        }
+    }
+
+    /// Stops the clock on the script. Call once the interpreter is done.
+    pub fn finish(&mut self) {
+        self.charge_line(Instant::now());
+        while !self.stack.is_empty() {
+            self.exit();
+        }
+    }
+
+    fn enter(&mut self, function: &str) {
+        self.stack.push(Activation {
+            function: function.to_string(),
+            started: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    fn exit(&mut self) {
+        let Some(activation) = self.stack.pop() else {
+            return;
+        };
+        let inclusive = activation.started.elapsed();
+        let exclusive = inclusive.saturating_sub(activation.children);
+
+        let mut path: Vec<&str> = self.stack.iter().map(|a| a.function.as_str()).collect();
+        path.push(&activation.function);
+        *self.folded.entry(path.join(";")).or_default() += exclusive;
+
+        // Recursive calls are already covered by the outermost activation's inclusive time.
+        let recursive = self.stack.iter().any(|a| a.function == activation.function);
+        let stats = self.functions.entry(activation.function).or_default();
+        stats.calls += 1;
+        stats.exclusive += exclusive;
+        if !recursive {
+            stats.inclusive += inclusive;
+        }
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += inclusive;
+        }
+    }
+
+    fn charge_line(&mut self, now: Instant) {
+        if let Some((line, started)) = self.current_line.take() {
+            self.lines.entry(line).or_default().time += now - started;
+        }
+    }
+
+    fn sorted_functions(&self) -> Vec<(&String, &FunctionStats)> {
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(b.0)));
+        functions
+    }
+
+    fn sorted_lines(&self) -> Vec<(usize, LineStats)> {
+        let mut lines: Vec<_> = self.lines.iter().map(|(line, stats)| (*line, *stats)).collect();
+        lines.sort_by(|a, b| b.1.time.cmp(&a.1.time).then(a.0.cmp(&b.0)));
+        lines
+    }
+
+    /// Functions sorted by exclusive time, then the ten hottest lines of `source`.
+    pub fn report(&self, source: &str) -> String {
+        let source: Vec<&str> = source.lines().collect();
+        let mut out = String::new();
+        let _ = writeln!(out, "{:<24} {:>8} {:>14} {:>14}", "Function", "Calls", "Inclusive ms", "Exclusive ms");
+        for (name, stats) in self.sorted_functions() {
+            let _ = writeln!(
+                out,
+                "{:<24} {:>8} {:>14.3} {:>14.3}",
+                name,
+                stats.calls,
+                millis(stats.inclusive),
+                millis(stats.exclusive)
+            );
+        }
+        let _ = writeln!(out);
+        let _ = writeln!(out, "{:>6} {:>8} {:>10}  Hot lines", "Line", "Hits", "Time ms");
+        for (line, stats) in self.sorted_lines().into_iter().take(10) {
+            let text = source.get(line.wrapping_sub(1)).map_or("", |text| text.trim());
+            let _ = writeln!(out, "{:>6} {:>8} {:>10.3}  {}", line, stats.hits, millis(stats.time), text);
+        }
+        out
+    }
+
+    /// One `stack;of;functions microseconds` line per distinct stack, as read by
+    /// `flamegraph.pl` and compatible tools.
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<_> = self.folded.iter().collect();
+        stacks.sort();
+        stacks
+            .into_iter()
+            .map(|(stack, time)| format!("{} {}\n", stack, time.as_micros()))
+            .collect()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let functions: Vec<Value> = self
+            .sorted_functions()
+            .into_iter()
+            .map(|(name, stats)| {
+                json!({
+                    "name": name,
+                    "calls": stats.calls,
+                    "inclusive_ms": millis(stats.inclusive),
+                    "exclusive_ms": millis(stats.exclusive)
+                })
+            })
+            .collect();
+        let lines: Vec<Value> = self
+            .sorted_lines()
+            .into_iter()
+            .map(|(line, stats)| json!({ "line": line, "hits": stats.hits, "time_ms": millis(stats.time) }))
+            .collect();
+        json!({ "functions": functions, "lines": lines, "folded": self.folded() })
+    }
+}
+
+impl Hook for Profiler {
+    fn statement(&mut self, stmt: &Stmt, _frames: &[Frame]) -> Result<(), String> {
+        // The script's clock starts with its first statement, not with parsing.
+        if self.stack.is_empty() {
+            self.enter("<script>");
+        }
+        let now = Instant::now();
+        self.charge_line(now);
+        self.lines.entry(stmt.line()).or_default().hits += 1;
+        self.current_line = Some((stmt.line(), now));
+        Ok(())
+    }
+
+    fn enter_function(&mut self, frames: &[Frame]) {
+        if let Some(frame) = frames.last() {
+            self.enter(&frame.function);
+        }
+    }
+
+    fn exit_function(&mut self, _frames: &[Frame]) {
+        self.exit();
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -1,44 +1,175 @@
 use crate::expr::Expr;
-use crate::token::Token;
+use crate::token::{Span, Token};
+use serde::Serialize;
+// Every statement records its `span`: the bytes from its first token through its closing
+// `;` or `}`, or through its body for `if`, `while` and `for`.
 #[allow(dead_code)]
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
+#[serde(tag = "type")]
 pub enum Stmt {
     Expression {
         expression: Expr,
+        line: usize,
+        span: Span,
     },
     Print {
         expression: Expr,
+        line: usize,
+        span: Span,
     },
     Var {
-        name: String,
+        name: Token,
+        type_annotation: Option<Token>, // `var x: num`
         initializer: Option<Expr>,
+        line: usize,
+        doc: Option<String>, // from `///` comments above the declaration
+        span: Span,
+    },
+    Block {
+        statements: Vec<Stmt>,
+        line: usize,
+        end_line: usize, // line of the closing '}'
+        span: Span,
     },
-    Block(Vec<Stmt>),
     If {
         condition: Expr,
         then_branch: Box<Stmt>,
         else_branch: Option<Box<Stmt>>,
+        line: usize,
+        span: Span,
     },
     While {
         condition: Expr,
         body: Box<Stmt>,
+        line: usize,
+        span: Span,
     },
     Input {
         name: Token,
+        span: Span,
     },
     For {
         initializer: Option<Box<Stmt>>,
         condition: Option<Expr>,
         increment: Option<Expr>,
         body: Box<Stmt>,
+        line: usize,
+        span: Span,
+    },
+    ForIn {
+        name: Token, // the loop variable
+        iterable: Expr,
+        body: Box<Stmt>,
+        line: usize,
+        span: Span,
     },
     Function {
         name: Token,
         params: Vec<Token>,
+        param_types: Vec<Option<Token>>, // one entry per parameter
+        return_type: Option<Token>,
         body: Vec<Stmt>,
+        end_line: usize, // line of the closing '}'
+        doc: Option<String>,
+        span: Span,
     },
     Return {
         keyword: Token,
         value: Option<Expr>,
+        span: Span,
+    },
+    Yield {
+        keyword: Token,
+        value: Option<Expr>,
+        span: Span,
+    },
+    Match {
+        subject: Expr,
+        arms: Vec<MatchArm>,
+        line: usize,
+        end_line: usize, // line of the closing '}'
+        span: Span,
+    },
+}
+
+/// `pattern if guard => body` inside a `match`.
+#[derive(Clone, Serialize)]
+pub struct MatchArm {
+    pub line: usize, // line the pattern starts on
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Stmt,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum Pattern {
+    /// A literal, matched with `==`.
+    Literal { value: Expr },
+    /// `low..high` or `low..=high`, matching numbers in between.
+    Range {
+        start: Box<Expr>,
+        operator: Token,
+        end: Box<Expr>,
     },
+    /// `_`, which matches anything.
+    Wildcard { token: Token },
+    /// A name, which matches anything and holds the value in the arm.
+    Binding { name: Token },
+}
+
+impl Stmt {
+    /// The source line the statement starts on.
+    pub fn line(&self) -> usize {
+        match self {
+            Stmt::Expression { line, .. }
+            | Stmt::Print { line, .. }
+            | Stmt::Var { line, .. }
+            | Stmt::Block { line, .. }
+            | Stmt::If { line, .. }
+            | Stmt::While { line, .. }
+            | Stmt::For { line, .. }
+            | Stmt::ForIn { line, .. }
+            | Stmt::Match { line, .. } => *line,
+            Stmt::Input { name, .. } => name.line,
+            Stmt::Function { name, .. } => name.line,
+            Stmt::Return { keyword, .. } | Stmt::Yield { keyword, .. } => keyword.line,
+        }
+    }
+
+    /// Byte range covered by the statement in the source.
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Expression { span, .. }
+            | Stmt::Print { span, .. }
+            | Stmt::Var { span, .. }
+            | Stmt::Block { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::While { span, .. }
+            | Stmt::Input { span, .. }
+            | Stmt::For { span, .. }
+            | Stmt::ForIn { span, .. }
+            | Stmt::Function { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Yield { span, .. }
+            | Stmt::Match { span, .. } => *span,
+        }
+    }
+
+    /// Whether a `yield` runs as part of this statement, which makes the enclosing function
+    /// a generator. Functions declared inside don't count.
+    pub fn contains_yield(&self) -> bool {
+        match self {
+            Stmt::Yield { .. } => true,
+            Stmt::Block { statements, .. } => statements.iter().any(Stmt::contains_yield),
+            Stmt::If {
+                then_branch,
+                else_branch,
+                ..
+            } => then_branch.contains_yield() || else_branch.as_ref().is_some_and(|s| s.contains_yield()),
+            Stmt::While { body, .. } | Stmt::For { body, .. } | Stmt::ForIn { body, .. } => body.contains_yield(),
+            Stmt::Match { arms, .. } => arms.iter().any(|arm| arm.body.contains_yield()),
+            _ => false,
+        }
+    }
 }
@@ -0,0 +1,119 @@
+// `test` subcommand: finds `*_test.aoi` files and runs every top-level `fun test_*()` in
+// them. Each test gets a fresh `Interpreter` that first runs the file's top-level code.
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::stmt::Stmt;
+use crate::token::Tokensizer;
+use crate::typechecker;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const FILE_SUFFIX: &str = "_test.aoi";
+pub const FUNCTION_PREFIX: &str = "test_";
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+/// Test files under `paths` (files are taken as given, directories are searched), sorted.
+pub fn discover(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            collect(path, &mut files);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files.sort();
+    files
+}
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            // Skip build output and hidden directories such as `.git`.
+            if name != "target" && !name.starts_with('.') {
+                collect(&path, files);
+            }
+        } else if name.ends_with(FILE_SUFFIX) {
+            files.push(path);
+        }
+    }
+}
+
+/// Runs the tests in one file, printing a line per test and details for failures.
+pub fn run_file(path: &Path, summary: &mut Summary) {
+    println!("{}", path.display());
+    let statements = match load(path) {
+        Ok(statements) => statements,
+        Err(err) => {
+            println!("  error   {}", indent(&err));
+            summary.failed += 1;
+            return;
+        }
+    };
+
+    let tests: Vec<&str> = statements
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::Function { name, .. } if name.lexeme.starts_with(FUNCTION_PREFIX) => Some(name.lexeme.as_str()),
+            _ => None,
+        })
+        .collect();
+    if tests.is_empty() {
+        println!("  (no {}* functions)", FUNCTION_PREFIX);
+    }
+
+    for test in tests {
+        let mut interpreter = Interpreter::new();
+        let setup = interpreter.interpret(&statements);
+        let result = match setup.lines().find(|line| line.starts_with("Runtime error: ")) {
+            Some(line) => Err(format!("top-level code failed: {}", line.trim_start_matches("Runtime error: "))),
+            None => interpreter.call_global(test),
+        };
+        match result {
+            Ok(()) => {
+                println!("  ok      {}", test);
+                summary.passed += 1;
+            }
+            Err(err) => {
+                println!("  FAILED  {}", test);
+                println!("    {}", indent(&err));
+                let output = &interpreter.output()[setup.len()..];
+                if !output.is_empty() {
+                    println!("    output:");
+                    for line in output.lines() {
+                        println!("      {}", line);
+                    }
+                }
+                summary.failed += 1;
+            }
+        }
+    }
+}
+
+fn load(path: &Path) -> Result<Vec<Stmt>, String> {
+    let source = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let tokens = Tokensizer::new(source).tokenize();
+    let statements = Parser::new(tokens)
+        .parse()
+        .ok_or_else(|| "Parsing failed due to syntax errors.".to_string())?;
+    let type_errors = typechecker::check(&statements);
+    if !type_errors.is_empty() {
+        return Err(type_errors.iter().map(|err| err.to_string()).collect::<Vec<_>>().join("\n"));
+    }
+    Ok(statements)
+}
+
+// Keeps multi-line failure messages lined up under the test name.
+fn indent(text: &str) -> String {
+    text.replace('\n', "\n    ")
+}
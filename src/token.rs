@@ -1,9 +1,13 @@
 use crate::error;
+use num_bigint::BigInt;
+use serde::{Serialize, Serializer};
 use std::fmt::Display;
-#[derive(Debug, Clone, PartialEq)] //	Allows println!("{:?}", obj); for debugging.  Allows obj.clone(); for copying data.
+#[derive(Debug, Clone, PartialEq, Serialize)] //	Allows println!("{:?}", obj); for debugging.  Allows obj.clone(); for copying data.
 #[allow(dead_code)] //Prevents warnings for unused code.
 pub enum TokenLiteral {
     String(String),
+    Integer(i64),
+    BigInteger(#[serde(serialize_with = "serialize_digits")] BigInt), // too large for `Integer`
     Number(f64),
     Identifier(String),
     Boolean(bool),
@@ -13,34 +17,201 @@ impl Display for TokenLiteral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TokenLiteral::String(s) => write!(f, "{}", s),
-            TokenLiteral::Number(n) => {
-                if n.fract() == 0.0 {
-                    write!(f, "{:.1}", n) // If integer, show as 12.0
-                } else {
-                    write!(f, "{}", n) // If decimal, show normally (e.g., 12.34)
-                }
-            }
+            TokenLiteral::Integer(n) => write!(f, "{}", n),
+            TokenLiteral::BigInteger(n) => write!(f, "{}", n),
+            TokenLiteral::Number(n) => write!(f, "{}", format_float(*n)),
             TokenLiteral::Boolean(b) => write!(f, "{}", b),
             TokenLiteral::Identifier(s) => write!(f, "{}", s),
             TokenLiteral::Null => write!(f, "null"),
         }
     }
 }
-#[derive(Debug, Clone, PartialEq)]
+// Big integers go into JSON as their decimal digits, which no JSON number can hold exactly.
+fn serialize_digits<S: Serializer>(n: &BigInt, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&n.to_string())
+}
+/// Writes a float so it reads back as a float: whole values keep a `.0` (`12.0`),
+/// and there is never an exponent.
+pub fn format_float(n: f64) -> String {
+    let text = n.to_string();
+    if n.is_finite() && !text.contains('.') {
+        format!("{}.0", text)
+    } else {
+        text
+    }
+}
+// Replaces the escape sequences in a string literal's text with the characters they stand for.
+fn unescape(text: &str) -> Result<String, String> {
+    let mut value = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        let escaped = match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('"') => '"',
+            Some('\'') => '\'',
+            Some('$') => '$',
+            Some('u') => unicode_escape(&mut chars)?,
+            Some(other) => return Err(format!("Invalid escape sequence '\\{}'.", other.escape_default())),
+            None => return Err("Invalid escape sequence '\\' at end of string.".to_string()),
+        };
+        value.push(escaped);
+    }
+    Ok(value)
+}
+
+// The rest of a `\u{1F600}` escape: one to six hex digits naming a Unicode scalar value.
+fn unicode_escape(chars: &mut std::str::Chars) -> Result<char, String> {
+    let rest = chars.as_str();
+    let digits = rest
+        .strip_prefix('{')
+        .and_then(|rest| rest.split_once('}'))
+        .map(|(digits, _)| digits)
+        .ok_or_else(|| "Invalid unicode escape: expected '\\u{...}'.".to_string())?;
+    let valid = (1..=6).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_hexdigit());
+    let c = u32::from_str_radix(digits, 16)
+        .ok()
+        .filter(|_| valid)
+        .and_then(char::from_u32)
+        .ok_or_else(|| format!("Invalid unicode escape '\\u{{{}}}'.", digits))?;
+    *chars = rest[digits.len() + 2..].chars();
+    Ok(c)
+}
+
+// `_` may only sit between two digits of a literal, so each run of digits in it must not
+// start or end with one or hold two in a row.
+fn separators(lexeme: &str, digit_runs: &[&str]) -> Result<(), String> {
+    for run in digit_runs {
+        if run.starts_with('_') || run.ends_with('_') || run.contains("__") {
+            return Err(format!("Invalid number '{}': '_' must be between digits.", lexeme));
+        }
+    }
+    Ok(())
+}
+
+// The text of a `"""` string, given as the parts around its `${...}` holes. A line break
+// right after the opening quotes is dropped, and so is the last line if it holds nothing
+// but the indentation of the closing quotes. The indentation shared by the remaining
+// non-blank lines (and the closing quotes) is removed.
+fn dedent(parts: &[String]) -> Vec<String> {
+    let mut parts = parts.to_vec();
+    if let Some(first) = parts.first_mut() {
+        if let Some(rest) = first.strip_prefix("\r\n").or_else(|| first.strip_prefix('\n')) {
+            *first = rest.to_string();
+        }
+    }
+    let mut closing_indent = None;
+    if let Some(last) = parts.last_mut() {
+        if let Some(newline) = last.rfind('\n') {
+            if last[newline + 1..].trim().is_empty() {
+                closing_indent = Some(last.len() - newline - 1);
+                last.truncate(newline);
+            }
+        }
+    }
+
+    // Where each line starts (part, byte offset), with the length of its indentation and
+    // whether the line is blank. A line whose indentation runs into a hole is not blank.
+    let last_part = parts.len().saturating_sub(1);
+    let mut lines = Vec::new();
+    for (index, part) in parts.iter().enumerate() {
+        let starts = std::iter::once(0).chain(part.match_indices('\n').map(|(at, _)| at + 1));
+        for (n, start) in starts.enumerate() {
+            if n == 0 && index > 0 {
+                continue; // continues the line the hole is on
+            }
+            let rest = &part[start..];
+            let indent = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+            let blank = match rest[indent..].chars().next() {
+                Some('\n' | '\r') => true,
+                Some(_) => false,
+                None => index == last_part,
+            };
+            lines.push((index, start, indent, blank));
+        }
+    }
+    let common = lines
+        .iter()
+        .filter(|(.., blank)| !blank)
+        .map(|&(_, _, indent, _)| indent)
+        .chain(closing_indent)
+        .min()
+        .unwrap_or(0);
+    for &(index, start, indent, _) in lines.iter().rev() {
+        parts[index].replace_range(start..start + indent.min(common), "");
+    }
+    parts.iter().map(|part| part.replace("\r\n", "\n")).collect()
+}
+
+/// Length in bytes of the `/* ... */` comment that `text` starts with, counting the
+/// comments nested in it, or `None` if it is never closed.
+pub fn block_comment_len(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i + 1 < bytes.len() {
+        match (bytes[i], bytes[i + 1]) {
+            (b'/', b'*') => {
+                depth += 1;
+                i += 2;
+            }
+            (b'*', b'/') => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Byte range of a token in the source it was scanned from.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<TokenLiteral>,
+    pub line: usize,
+    pub span: Span,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>, // the `///` comment lines right above the token, without the `///`
 }
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, literal: TokenLiteral) -> Self {
+    pub fn new(token_type: TokenType, lexeme: String, literal: TokenLiteral, line: usize) -> Self {
         Self {
             token_type,
             lexeme,
             literal: Some(literal),
+            line,
+            span: Span::default(),
+            doc: None,
         }
     }
 }
+/// A `//` or `/* */` comment skipped by the tokenizer, kept so tools like the formatter can
+/// put it back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub line: usize, // where it starts; a block comment may run over several lines
+    pub trailing: bool, // true when code precedes the comment on the same line
+    pub span: Span,
+}
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -50,8 +221,8 @@ impl Display for Token {
         )
     }
 }
-#[allow(dead_code, non_camel_case_types)]
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(dead_code, non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub enum TokenType {
     // Single-character tokens.
     LEFT_PAREN,
@@ -61,21 +232,41 @@ pub enum TokenType {
     RIGHT_BRACKET,
     LEFT_BRACKET,
     COMMA,
+    COLON,
     DOT,
     MINUS,
     PLUS,
     SEMICOLON,
     SLASH,
     STAR,
+    PERCENT,
+    AMPERSAND,
+    PIPE,
+    CARET,
+    TILDE,
     // One or two character tokens.
     BANG,
     BANG_EQUAL,
     EQUAL,
     EQUAL_EQUAL,
+    EQUAL_GREATER,
     GREATER,
     GREATER_EQUAL,
     LESS,
     LESS_EQUAL,
+    PLUS_EQUAL,
+    MINUS_EQUAL,
+    STAR_EQUAL,
+    SLASH_EQUAL,
+    PERCENT_EQUAL,
+    PLUS_PLUS,
+    MINUS_MINUS,
+    STAR_STAR,
+    TILDE_SLASH,
+    LESS_LESS,
+    GREATER_GREATER,
+    DOT_DOT,
+    DOT_DOT_EQUAL,
 
     // Literals.
     IDENTIFIER,
@@ -90,6 +281,8 @@ pub enum TokenType {
     FUN,
     FOR,
     IF,
+    IN,
+    MATCH,
     NIL,
     OR,
     PRINT,
@@ -99,12 +292,53 @@ pub enum TokenType {
     TRUE,
     VAR,
     WHILE,
+    YIELD,
     EOF,
     SCAN,
+
+    // A string the tokenizer reported an error in. Its literal is the error message.
+    ERROR,
+
+    // The parts of a string with `${...}` in it: `"text ${` and `} text ${` are
+    // INTERPOLATION, and the closing `} text"` is INTERPOLATION_END.
+    INTERPOLATION,
+    INTERPOLATION_END,
+}
+impl TokenType {
+    /// The arithmetic operator a compound assignment or increment applies: `+=` and `++`
+    /// both add.
+    pub fn arithmetic_base(self) -> Option<TokenType> {
+        match self {
+            TokenType::PLUS_EQUAL | TokenType::PLUS_PLUS => Some(TokenType::PLUS),
+            TokenType::MINUS_EQUAL | TokenType::MINUS_MINUS => Some(TokenType::MINUS),
+            TokenType::STAR_EQUAL => Some(TokenType::STAR),
+            TokenType::SLASH_EQUAL => Some(TokenType::SLASH),
+            TokenType::PERCENT_EQUAL => Some(TokenType::PERCENT),
+            _ => None,
+        }
+    }
+}
+// A string whose `${...}` is being scanned, to resume once the matching `}` comes.
+struct PendingString {
+    line: usize,
+    parts: Vec<usize>, // indices in `tokens` of the parts scanned so far
+    triple: bool,
+    raw: bool,
+    braces: usize, // `{` opened inside the interpolation and not yet closed
 }
+// Characters that start a symbol token; see `scan_token`.
+const PUNCTUATION: &str = "[](){},:.-+;*%~&|^=!<>/";
+
+// How much of a line an error message shows on each side of the problem.
+const CONTEXT_CHARS: usize = 80;
+
 pub struct Tokensizer {
     src: String,
     tokens: Vec<Token>,
+    comments: Vec<Comment>,
+    interpolations: Vec<PendingString>,
+    doc: Vec<String>, // `///` lines waiting for the next token
+    had_error: bool,  // something was reported, see `had_error()`
     start: usize,
     current: usize,
     line: usize,
@@ -114,6 +348,10 @@ impl Tokensizer {
         Self {
             src,
             tokens: Vec::new(),
+            comments: Vec::new(),
+            interpolations: Vec::new(),
+            doc: Vec::new(),
+            had_error: false,
             start: 0,
             current: 0,
             line: 1,
@@ -125,89 +363,307 @@ impl Tokensizer {
 
     fn add_token(&mut self, token_type: TokenType, literal: TokenLiteral) {
         let text = self.src[self.start..self.current].to_string();
-        self.tokens.push(Token::new(token_type, text, literal));
+        let mut token = Token::new(token_type, text, literal, self.line);
+        token.span = Span {
+            start: self.start,
+            end: self.current,
+        };
+        if !self.doc.is_empty() {
+            token.doc = Some(std::mem::take(&mut self.doc).join("\n"));
+        }
+        self.tokens.push(token);
     }
 
+    // `start` and `current` are byte offsets, so they always fall on char boundaries.
     fn advance(&mut self) -> char {
-        self.current += 1;
-        self.src.chars().nth(self.current - 1).unwrap()
+        let c = self.peek().unwrap_or('\0');
+        self.current += c.len_utf8();
+        c
     }
 
     fn peek(&self) -> Option<char> {
-        self.src.chars().nth(self.current)
+        self.src[self.current..].chars().next()
     }
 
-    //this function is used to scan the string
-    fn string(&mut self) {
-        while self.peek() != Some('"') && !self.is_at_end() {
-            if self.peek() == Some('\n') {
-                self.line += 1;
+    // Scans a string after its opening quote: `"..."`, `"""..."""` (multi-line, with the
+    // common indentation removed) or, when `raw`, either form without escapes or `${...}`.
+    fn string(&mut self, raw: bool) {
+        let triple = self.src[self.current..].starts_with("\"\"");
+        if triple {
+            self.current += 2;
+        }
+        let pending = PendingString {
+            line: self.line,
+            parts: Vec::new(),
+            triple,
+            raw,
+            braces: 0,
+        };
+        self.string_part(pending);
+    }
+
+    // Scans string text up to the closing quotes or, outside raw strings, the next `${`,
+    // which makes this part an INTERPOLATION and resumes normal scanning until the
+    // matching `}`.
+    fn string_part(&mut self, mut pending: PendingString) {
+        let delimiter = if pending.triple { "\"\"\"" } else { "\"" };
+        let content_start = self.current;
+        let mut interpolation = false;
+        while !self.is_at_end() && !self.src[self.current..].starts_with(delimiter) {
+            if !pending.raw && self.src[self.current..].starts_with("${") {
+                interpolation = true;
+                break;
+            }
+            match self.advance() {
+                '\n' => self.line += 1,
+                // Skip the escaped character so `\"` doesn't end the string.
+                '\\' if !pending.raw && self.peek().is_some_and(|c| c != '\n') => {
+                    self.advance();
+                }
+                _ => {}
             }
-            self.advance();
         }
 
         if self.is_at_end() {
             error::error(self.line, "Unterminated string", "");
+            self.had_error = true;
+            return;
+        }
+        let content = self.src[content_start..self.current].to_string();
+        if interpolation {
+            self.current += 2;
+            pending.parts.push(self.tokens.len());
+            self.add_token(TokenType::INTERPOLATION, TokenLiteral::String(content));
+            self.interpolations.push(pending);
             return;
         }
-        self.advance();
+        self.current += delimiter.len();
+        let token_type = if pending.parts.is_empty() {
+            TokenType::STRING
+        } else {
+            TokenType::INTERPOLATION_END
+        };
+        pending.parts.push(self.tokens.len());
+        self.add_token(token_type, TokenLiteral::String(content));
+        self.finish_string(pending);
+    }
 
-        // Extract the string value without the surrounding quotes
-        let value = self.src[self.start + 1..self.current - 1].to_string();
-        self.add_token(TokenType::STRING, TokenLiteral::String(value));
+    // Turns the raw text of each part of a finished string into its value. A string with an
+    // invalid escape becomes a single ERROR token, interpolated expressions and all.
+    fn finish_string(&mut self, pending: PendingString) {
+        let texts: Vec<String> = pending
+            .parts
+            .iter()
+            .map(|&index| match &self.tokens[index].literal {
+                Some(TokenLiteral::String(text)) => text.clone(),
+                _ => String::new(),
+            })
+            .collect();
+        let texts = if pending.triple { dedent(&texts) } else { texts };
+        let values: Result<Vec<String>, String> = if pending.raw {
+            Ok(texts)
+        } else {
+            texts.iter().map(|text| unescape(text)).collect()
+        };
+        match values {
+            Ok(values) => {
+                for (&index, value) in pending.parts.iter().zip(values) {
+                    self.tokens[index].literal = Some(TokenLiteral::String(value));
+                }
+            }
+            Err(message) => {
+                error::error(pending.line, &message, "");
+                self.had_error = true;
+                let first = pending.parts[0];
+                self.start = self.tokens[first].span.start;
+                self.tokens.truncate(first);
+                self.add_token(TokenType::ERROR, TokenLiteral::String(message));
+            }
+        }
     }
 
     fn isdigit(c: char) -> bool {
         c.is_ascii_digit()
     }
 
+    // Scans a number literal whose first digit (or `.`) is already consumed: a decimal with
+    // an optional fraction and exponent, or a `0x`, `0b` or `0o` integer, with `_` allowed
+    // between digits. A malformed literal becomes an ERROR token.
     fn number(&mut self) {
-        while self.peek().map_or(false, Self::isdigit) {
-            self.advance();
+        let prefix = self.peek().map(|c| c.to_ascii_lowercase());
+        let literal = match prefix {
+            Some('x') if &self.src[self.start..self.current] == "0" => self.radix_number(16, "hexadecimal"),
+            Some('b') if &self.src[self.start..self.current] == "0" => self.radix_number(2, "binary"),
+            Some('o') if &self.src[self.start..self.current] == "0" => self.radix_number(8, "octal"),
+            _ => self.decimal_number(),
+        };
+        match literal {
+            Ok(literal) => self.add_token(TokenType::NUMBER, literal),
+            Err(message) => {
+                error::error(self.line, &message, "");
+                self.had_error = true;
+                self.add_token(TokenType::ERROR, TokenLiteral::String(message));
+            }
         }
+    }
 
-        // Look for a fractional part
-        let mut _has_fraction = false;
-        if self.peek() == Some('.') {
-            // Ensure the next character is a digit before consuming the dot
-            if self.peek_next().map_or(false, Self::isdigit) {
-                _has_fraction = true;
-                self.advance(); // Consume the '.'
+    // `0x1F`, `0b1010`, `0o755`: always an integer, big if it must be.
+    fn radix_number(&mut self, radix: u32, name: &str) -> Result<TokenLiteral, String> {
+        self.advance(); // the `x`, `b` or `o`
+        let from = self.current;
+        // Take every letter and digit, so `0x1G` is one bad literal rather than `0x1` and `G`.
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') {
+            self.advance();
+        }
+        let lexeme = &self.src[self.start..self.current];
+        let digits = &self.src[from..self.current];
+        if digits.chars().all(|c| c == '_') {
+            return Err(format!("Invalid number '{}': expected {} digits.", lexeme, name));
+        }
+        if let Some(bad) = digits.chars().find(|&c| c != '_' && !c.is_digit(radix)) {
+            return Err(format!("Invalid number '{}': '{}' is not a valid {} digit.", lexeme, bad, name));
+        }
+        separators(lexeme, &[digits])?;
+        let value = BigInt::parse_bytes(digits.replace('_', "").as_bytes(), radix)
+            .ok_or_else(|| format!("Invalid number '{}'.", lexeme))?;
+        Ok(match i64::try_from(&value) {
+            Ok(n) => TokenLiteral::Integer(n),
+            Err(_) => TokenLiteral::BigInteger(value),
+        })
+    }
 
-                while self.peek().map_or(false, Self::isdigit) {
-                    self.advance();
-                }
+    // `42`, `1_000`, `3.14`, `.5`, `1e-9`: an integer unless it has a fraction or exponent.
+    fn decimal_number(&mut self) -> Result<TokenLiteral, String> {
+        let leading_dot = self.src[self.start..].starts_with('.');
+        let mut groups = vec![self.digit_run(if leading_dot { self.start + 1 } else { self.start })];
+        let mut float = leading_dot;
+        if !leading_dot && self.peek() == Some('.') && self.peek_next().is_some_and(Self::isdigit) {
+            self.advance();
+            float = true;
+            groups.push(self.digit_run(self.current));
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.advance();
+            float = true;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.advance();
+            }
+            let exponent = self.digit_run(self.current);
+            if exponent.is_empty() {
+                let lexeme = &self.src[self.start..self.current];
+                return Err(format!("Invalid number '{}': expected digits in the exponent.", lexeme));
             }
+            groups.push(exponent);
         }
 
-        // Convert the lexeme to a floating-point number
-
-        // If no fractional part exists, ensure .0 is appended
-        // Convert the lexeme to a floating-point number
         let lexeme = &self.src[self.start..self.current];
-        let float_lexeme = if lexeme.contains('.') {
-            lexeme.to_string()
-        } else {
-            format!("{}.0", lexeme) // Append .0 if it's an integer
-        };
+        let groups: Vec<&str> = groups.into_iter().map(|range| &self.src[range]).collect();
+        separators(lexeme, &groups)?;
+        let text = lexeme.replace('_', "");
+        if float {
+            return match text.parse::<f64>() {
+                Ok(value) if value.is_finite() => Ok(TokenLiteral::Number(value)),
+                _ => Err(format!("Invalid number '{}': too large for a float.", lexeme)),
+            };
+        }
+        if let Ok(value) = text.parse::<i64>() {
+            return Ok(TokenLiteral::Integer(value));
+        }
+        text.parse::<BigInt>()
+            .map(TokenLiteral::BigInteger)
+            .map_err(|_| format!("Invalid number '{}'.", lexeme))
+    }
 
-        if let Ok(value) = float_lexeme.parse::<f64>() {
-            self.add_token(TokenType::NUMBER, TokenLiteral::Number(value));
-        } else {
-            error::error(self.line, &format!("Invalid number format: {}", lexeme), "");
+    // Consumes digits and `_` from the current position; `from` is where the run began.
+    fn digit_run(&mut self, from: usize) -> std::ops::Range<usize> {
+        while self.peek().is_some_and(|c| Self::isdigit(c) || c == '_') {
+            self.advance();
+        }
+        from..self.current
+    }
+
+    // The token type paired with the next character if it is one of `options` (consuming
+    // it), otherwise `single`.
+    fn pick(&mut self, options: &[(char, TokenType)], single: TokenType) -> TokenType {
+        for &(next, token_type) in options {
+            if self.peek() == Some(next) {
+                self.current += 1;
+                return token_type;
+            }
         }
+        single
+    }
+
+    // `/* ... */`, which may span lines and hold other block comments.
+    fn block_comment(&mut self) {
+        let line = self.line;
+        let trailing = self.tokens.last().is_some_and(|t| t.line == line);
+        let rest = &self.src[self.start..];
+        let len = block_comment_len(rest).unwrap_or_else(|| {
+            error::error(line, "Unterminated block comment", "");
+            self.had_error = true;
+            rest.len()
+        });
+        let text = rest[..len].to_string();
+        self.line += text.matches('\n').count();
+        self.current = self.start + len;
+        let span = Span {
+            start: self.start,
+            end: self.current,
+        };
+        self.comments.push(Comment {
+            text,
+            line,
+            trailing,
+            span,
+        });
+    }
+
+    // The source line the current token is on, found from `start` so that reporting an
+    // error doesn't rescan the file. Only CONTEXT_CHARS characters either side are shown, so
+    // errors on a very long line don't each copy the whole line.
+    fn current_line(&self) -> &str {
+        let begin = self.src[..self.start]
+            .char_indices()
+            .rev()
+            .take(CONTEXT_CHARS)
+            .take_while(|(_, c)| *c != '\n')
+            .last()
+            .map_or(self.start, |(at, _)| at);
+        let end = self.src[self.start..]
+            .char_indices()
+            .take(CONTEXT_CHARS)
+            .take_while(|(_, c)| *c != '\n')
+            .last()
+            .map_or(self.start, |(at, c)| self.start + at + c.len_utf8());
+        self.src[begin..end].trim_end_matches('\r')
+    }
+
+    // Whether `scan_token` knows what to do with `c`; a run of characters it doesn't is
+    // reported as one error.
+    fn starts_token(c: char) -> bool {
+        matches!(c, ' ' | '\r' | '\t' | '\n' | '"')
+            || c.is_ascii_digit()
+            || Self::isalpha(c)
+            || PUNCTUATION.contains(c)
     }
 
     fn peek_next(&self) -> Option<char> {
-        self.src.chars().nth(self.current + 1)
+        self.src[self.current..].chars().nth(1)
     }
 
+    // Identifiers start with a letter from any script or `_`, and go on with letters,
+    // digits and `_`: `café`, `名前`, `x2`.
     fn isalpha(c: char) -> bool {
-        c.is_ascii_alphabetic() || c == '_'
+        c.is_alphabetic() || c == '_'
+    }
+
+    fn isalnum(c: char) -> bool {
+        c.is_alphanumeric() || c == '_'
     }
 
     fn identifier(&mut self) {
-        while self.peek().map_or(false, Self::isalpha) {
+        while self.peek().is_some_and(Self::isalnum) {
             self.advance();
         }
 
@@ -220,6 +676,8 @@ impl Tokensizer {
             "for" => TokenType::FOR,
             "fun" => TokenType::FUN,
             "if" => TokenType::IF,
+            "in" => TokenType::IN,
+            "match" => TokenType::MATCH,
             "nil" => TokenType::NIL,
             "or" => TokenType::OR,
             "write" => TokenType::PRINT,
@@ -229,6 +687,7 @@ impl Tokensizer {
             "true" => TokenType::TRUE,
             "var" => TokenType::VAR,
             "while" => TokenType::WHILE,
+            "yield" => TokenType::YIELD,
             "scan" => TokenType::SCAN,
             _ => TokenType::IDENTIFIER,
         };
@@ -243,37 +702,77 @@ impl Tokensizer {
             ']' => self.add_token(TokenType::RIGHT_BRACKET, TokenLiteral::Null),
             '(' => self.add_token(TokenType::LEFT_PAREN, TokenLiteral::Null),
             ')' => self.add_token(TokenType::RIGHT_PAREN, TokenLiteral::Null),
-            '{' => self.add_token(TokenType::LEFT_BRACE, TokenLiteral::Null),
-            '}' => self.add_token(TokenType::RIGHT_BRACE, TokenLiteral::Null),
+            '{' => {
+                if let Some(pending) = self.interpolations.last_mut() {
+                    pending.braces += 1;
+                }
+                self.add_token(TokenType::LEFT_BRACE, TokenLiteral::Null);
+            }
+            '}' => match self.interpolations.pop() {
+                Some(pending) if pending.braces == 0 => self.string_part(pending),
+                Some(mut pending) => {
+                    pending.braces -= 1;
+                    self.interpolations.push(pending);
+                    self.add_token(TokenType::RIGHT_BRACE, TokenLiteral::Null);
+                }
+                None => self.add_token(TokenType::RIGHT_BRACE, TokenLiteral::Null),
+            },
             ',' => self.add_token(TokenType::COMMA, TokenLiteral::Null),
+            ':' => self.add_token(TokenType::COLON, TokenLiteral::Null),
             '.' => {
-                if self.peek().map_or(false, |c| c.is_digit(10)) {
-                    self.advance(); // Consume '.'
-                    while self.peek().map_or(false, |c| c.is_digit(10)) {
-                        self.advance();
-                    }
-
-                    let value: f64 = self.src[self.start..self.current].parse().unwrap();
-                    self.add_token(TokenType::NUMBER, TokenLiteral::Number(value));
+                if self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.number(); // `.5`
+                } else if self.peek() == Some('.') {
+                    self.current += 1;
+                    let token_type = self.pick(&[('=', TokenType::DOT_DOT_EQUAL)], TokenType::DOT_DOT);
+                    self.add_token(token_type, TokenLiteral::Null);
                 } else {
                     self.add_token(TokenType::DOT, TokenLiteral::Null);
                 }
             }
 
-            '-' => self.add_token(TokenType::MINUS, TokenLiteral::Null),
-            '+' => self.add_token(TokenType::PLUS, TokenLiteral::Null),
+            '-' => {
+                let token_type = self.pick(
+                    &[('=', TokenType::MINUS_EQUAL), ('-', TokenType::MINUS_MINUS)],
+                    TokenType::MINUS,
+                );
+                self.add_token(token_type, TokenLiteral::Null);
+            }
+            '+' => {
+                let token_type = self.pick(
+                    &[('=', TokenType::PLUS_EQUAL), ('+', TokenType::PLUS_PLUS)],
+                    TokenType::PLUS,
+                );
+                self.add_token(token_type, TokenLiteral::Null);
+            }
             ';' => self.add_token(TokenType::SEMICOLON, TokenLiteral::Null),
-            '*' => self.add_token(TokenType::STAR, TokenLiteral::Null),
+            '*' => {
+                let token_type = self.pick(
+                    &[('=', TokenType::STAR_EQUAL), ('*', TokenType::STAR_STAR)],
+                    TokenType::STAR,
+                );
+                self.add_token(token_type, TokenLiteral::Null);
+            }
+            '%' => {
+                let token_type = self.pick(&[('=', TokenType::PERCENT_EQUAL)], TokenType::PERCENT);
+                self.add_token(token_type, TokenLiteral::Null);
+            }
+            '~' => {
+                let token_type = self.pick(&[('/', TokenType::TILDE_SLASH)], TokenType::TILDE);
+                self.add_token(token_type, TokenLiteral::Null);
+            }
+            '&' => self.add_token(TokenType::AMPERSAND, TokenLiteral::Null),
+            '|' => self.add_token(TokenType::PIPE, TokenLiteral::Null),
+            '^' => self.add_token(TokenType::CARET, TokenLiteral::Null),
             '=' => {
-                if self.src.chars().nth(self.current).unwrap() == '=' {
-                    self.current += 1;
-                    self.add_token(TokenType::EQUAL_EQUAL, TokenLiteral::Null);
-                } else {
-                    self.add_token(TokenType::EQUAL, TokenLiteral::Null);
-                }
+                let token_type = self.pick(
+                    &[('=', TokenType::EQUAL_EQUAL), ('>', TokenType::EQUAL_GREATER)],
+                    TokenType::EQUAL,
+                );
+                self.add_token(token_type, TokenLiteral::Null);
             }
             '!' => {
-                if self.src.chars().nth(self.current).unwrap() == '=' {
+                if self.peek() == Some('=') {
                     //check the next one if it is equal then add the token
                     self.current += 1;
                     self.add_token(TokenType::BANG_EQUAL, TokenLiteral::Null);
@@ -283,31 +782,48 @@ impl Tokensizer {
                 }
             }
             '<' => {
-                if self.src.chars().nth(self.current) == Some('=') {
-                    // ✅ Safe check
-                    self.current += 1;
-                    self.add_token(TokenType::LESS_EQUAL, TokenLiteral::Null);
-                } else {
-                    self.add_token(TokenType::LESS, TokenLiteral::Null);
-                }
+                let token_type = self.pick(
+                    &[('=', TokenType::LESS_EQUAL), ('<', TokenType::LESS_LESS)],
+                    TokenType::LESS,
+                );
+                self.add_token(token_type, TokenLiteral::Null);
             }
 
             '>' => {
-                if self.src.chars().nth(self.current) == Some('=') {
-                    //check the next one if it is equal then add the token
-                    self.current += 1;
-                    self.add_token(TokenType::GREATER_EQUAL, TokenLiteral::Null);
-                } else {
-                    //else add Greater token
-                    self.add_token(TokenType::GREATER, TokenLiteral::Null);
-                }
+                let token_type = self.pick(
+                    &[('=', TokenType::GREATER_EQUAL), ('>', TokenType::GREATER_GREATER)],
+                    TokenType::GREATER,
+                );
+                self.add_token(token_type, TokenLiteral::Null);
             }
             '/' => {
-                if self.src.chars().nth(self.current) == Some('/') {
+                if self.peek() == Some('/') {
                     //check the next one if it is equal then add the token used some because it returns an option and we are getting initially none as unwrap cant be none
-                    while self.src.chars().nth(self.current) != Some('\n') && !self.is_at_end() {
-                        self.current += 1;
+                    while self.peek() != Some('\n') && !self.is_at_end() {
+                        self.advance();
                     }
+                    let trailing = self.tokens.last().is_some_and(|t| t.line == self.line);
+                    let text = self.src[self.start..self.current].trim_end().to_string();
+                    // `///` documents the declaration below it; `////` is an ordinary comment.
+                    if !trailing && text.starts_with("///") && !text.starts_with("////") {
+                        let doc = &text[3..];
+                        self.doc.push(doc.strip_prefix(' ').unwrap_or(doc).to_string());
+                    }
+                    let span = Span {
+                        start: self.start,
+                        end: self.start + text.len(),
+                    };
+                    self.comments.push(Comment {
+                        text,
+                        line: self.line,
+                        trailing,
+                        span,
+                    });
+                } else if self.peek() == Some('*') {
+                    self.block_comment();
+                } else if self.peek() == Some('=') {
+                    self.current += 1;
+                    self.add_token(TokenType::SLASH_EQUAL, TokenLiteral::Null);
                 } else {
                     self.add_token(TokenType::SLASH, TokenLiteral::Null);
                 }
@@ -320,25 +836,30 @@ impl Tokensizer {
                 self.line += 1; // Track line numbers correctly
             }
 
-            '"' => self.string(), //here we are calling the string function
+            '"' => self.string(false), //here we are calling the string function
+            'r' if self.peek() == Some('"') => {
+                self.advance();
+                self.string(true);
+            }
 
             '0'..='9' => self.number(), // Call number() when encountering a digit
 
             _ if Self::isalpha(c) => self.identifier(),
 
             _ => {
-                let line_content: String = self
-                    .src
-                    .lines()
-                    .nth(self.line - 1)
-                    .unwrap_or("")
-                    .to_string();
-
-                error::error(
-                    self.line,
-                    &format!("Unexpected character: '{}'", c),
-                    &line_content,
-                );
+                while self.peek().is_some_and(|next| !Self::starts_token(next)) {
+                    self.advance();
+                }
+                let run = &self.src[self.start..self.current];
+                let message = if run.len() == c.len_utf8() {
+                    format!("Unexpected character: '{}'", c)
+                } else {
+                    let shown: String = run.chars().take(CONTEXT_CHARS).collect();
+                    let more = if shown.len() < run.len() { "..." } else { "" };
+                    format!("Unexpected characters: '{}{}'", shown, more)
+                };
+                error::error(self.line, &message, self.current_line());
+                self.had_error = true;
             }
         }
     }
@@ -348,10 +869,31 @@ impl Tokensizer {
             self.start = self.current;
             self.scan_token();
         }
-        self.tokens
-            .push(Token::new(TokenType::EOF, "".into(), TokenLiteral::Null));
+        if let Some(pending) = self.interpolations.pop() {
+            error::error(pending.line, "Unterminated string interpolation", "");
+            self.had_error = true;
+            self.interpolations.clear();
+        }
+        let mut eof = Token::new(TokenType::EOF, "".into(), TokenLiteral::Null, self.line);
+        eof.span = Span {
+            start: self.current,
+            end: self.current,
+        };
+        self.tokens.push(eof);
         self.tokens.clone()
     }
+
+    /// Comments seen by the last call to `tokenize`, in source order.
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    /// Whether `tokenize` reported an error, such as an unexpected character or an
+    /// unterminated string. The tokens are still usable, but some source text was dropped
+    /// or replaced by an ERROR token.
+    pub fn had_error(&self) -> bool {
+        self.had_error
+    }
     // pub fn print_tokens(&self) {
     //     for token in &self.tokens {
     //         println!("{}", token);
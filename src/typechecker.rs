@@ -0,0 +1,631 @@
+// Gradual type checker. Annotations (`var x: num`, `fun f(a: str): bool`) and literals give
+// types; everything else is `Any`. Only mismatches that involve an annotation are reported,
+// so unannotated code runs exactly as it would without the checker.
+use crate::expr::Expr;
+use crate::stmt::{Pattern, Stmt};
+use crate::token::{Token, TokenLiteral, TokenType};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Num,
+    Str,
+    Bool,
+    Nil,
+    Fun,
+    Range,
+    Any,
+}
+
+impl Type {
+    fn from_annotation(token: &Token) -> Option<Type> {
+        match token.lexeme.as_str() {
+            "num" => Some(Type::Num),
+            "str" => Some(Type::Str),
+            "bool" => Some(Type::Bool),
+            "nil" => Some(Type::Nil),
+            "fun" => Some(Type::Fun),
+            "range" => Some(Type::Range),
+            "any" => Some(Type::Any),
+            _ => None,
+        }
+    }
+
+    // `Any` is compatible with everything in both directions.
+    fn accepts(self, other: Type) -> bool {
+        self == Type::Any || other == Type::Any || self == other
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Type::Num => "num",
+            Type::Str => "str",
+            Type::Bool => "bool",
+            Type::Nil => "nil",
+            Type::Fun => "fun",
+            Type::Range => "range",
+            Type::Any => "any",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] Type error: {}", self.line, self.message)
+    }
+}
+
+// A type together with whether an annotation vouches for it. Literal types alone never
+// produce errors; they only matter when they meet an annotated type.
+#[derive(Debug, Clone, Copy)]
+struct Typed {
+    ty: Type,
+    declared: bool,
+}
+
+impl Typed {
+    fn inferred(ty: Type) -> Typed {
+        Typed { ty, declared: false }
+    }
+
+    fn any() -> Typed {
+        Typed::inferred(Type::Any)
+    }
+
+    fn or(self, other: Typed) -> bool {
+        self.declared || other.declared
+    }
+}
+
+#[derive(Clone)]
+enum Binding {
+    Value(Typed),
+    Function { params: Vec<Type>, returns: Typed },
+}
+
+/// Checks a parsed program and returns every mismatch found, in source order.
+pub fn check(statements: &[Stmt]) -> Vec<TypeError> {
+    let mut checker = TypeChecker {
+        scopes: vec![HashMap::new()],
+        return_types: Vec::new(),
+        errors: Vec::new(),
+    };
+    checker.statements(statements);
+    checker.errors
+}
+
+struct TypeChecker {
+    scopes: Vec<HashMap<String, Binding>>,
+    return_types: Vec<Type>, // declared return type of each enclosing function
+    errors: Vec<TypeError>,
+}
+
+impl TypeChecker {
+    fn statements(&mut self, statements: &[Stmt]) {
+        // Functions can be called from bodies declared before them, so register signatures first.
+        for stmt in statements {
+            if let Stmt::Function { .. } = stmt {
+                self.declare_function(stmt);
+            }
+        }
+        for stmt in statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expression { expression, .. } | Stmt::Print { expression, .. } => {
+                self.expression(expression);
+            }
+            Stmt::Var {
+                name,
+                type_annotation,
+                initializer,
+                ..
+            } => {
+                let declared = self.annotation(type_annotation);
+                if let Some(init) = initializer {
+                    let actual = self.expression(init).ty;
+                    if !declared.ty.accepts(actual) {
+                        self.error(
+                            name.line,
+                            format!("Variable '{}' is declared {} but initialized with {}.", name.lexeme, declared.ty, actual),
+                        );
+                    }
+                }
+                self.define(&name.lexeme, Binding::Value(declared));
+            }
+            Stmt::Block { statements, .. } => {
+                self.scopes.push(HashMap::new());
+                self.statements(statements);
+                self.scopes.pop();
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                line,
+                ..
+            } => {
+                let actual = self.expression(condition);
+                if actual.declared && !Type::Bool.accepts(actual.ty) {
+                    self.error(*line, format!("Condition must be a boolean, found {}.", actual.ty));
+                }
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.expression(condition);
+                self.statement(body);
+            }
+            Stmt::Input { .. } => {}
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                self.scopes.push(HashMap::new());
+                if let Some(init) = initializer {
+                    self.statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.expression(condition);
+                }
+                if let Some(increment) = increment {
+                    self.expression(increment);
+                }
+                self.statement(body);
+                self.scopes.pop();
+            }
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+                line,
+                ..
+            } => {
+                let iterable = self.expression(iterable);
+                let element = match iterable.ty {
+                    Type::Range => Type::Num,
+                    Type::Str => Type::Str,
+                    Type::Any => Type::Any,
+                    other => {
+                        if iterable.declared {
+                            self.error(*line, format!("Cannot iterate over {}.", other));
+                        }
+                        Type::Any
+                    }
+                };
+                self.scopes.push(HashMap::new());
+                self.define(&name.lexeme, Binding::Value(Typed { ty: element, declared: iterable.declared }));
+                self.statement(body);
+                self.scopes.pop();
+            }
+            Stmt::Function {
+                params,
+                param_types,
+                return_type,
+                body,
+                ..
+            } => {
+                self.declare_function(stmt);
+                self.scopes.push(HashMap::new());
+                for (param, ty) in params.iter().zip(param_types) {
+                    let ty = self.annotation(ty);
+                    self.define(&param.lexeme, Binding::Value(ty));
+                }
+                let returns = self.annotation(return_type).ty;
+                self.return_types.push(returns);
+                self.statements(body);
+                self.return_types.pop();
+                self.scopes.pop();
+            }
+            Stmt::Return { keyword, value, .. } => {
+                let actual = match value {
+                    Some(value) => self.expression(value).ty,
+                    None => Type::Nil,
+                };
+                if let Some(&expected) = self.return_types.last() {
+                    if !expected.accepts(actual) {
+                        self.error(
+                            keyword.line,
+                            format!("Function returns {} but is declared to return {}.", actual, expected),
+                        );
+                    }
+                }
+            }
+            Stmt::Match { subject, arms, .. } => {
+                let subject = self.expression(subject);
+                for arm in arms {
+                    self.scopes.push(HashMap::new());
+                    self.pattern(&arm.pattern, subject, arm.line);
+                    if let Some(guard) = &arm.guard {
+                        let actual = self.expression(guard);
+                        if actual.declared && !Type::Bool.accepts(actual.ty) {
+                            self.error(arm.line, format!("Match guard must be a boolean, found {}.", actual.ty));
+                        }
+                    }
+                    self.statement(&arm.body);
+                    self.scopes.pop();
+                }
+            }
+            // A generator's return annotation is the type of the values it yields.
+            Stmt::Yield { keyword, value, .. } => {
+                let actual = match value {
+                    Some(value) => self.expression(value).ty,
+                    None => Type::Nil,
+                };
+                if let Some(&expected) = self.return_types.last() {
+                    if !expected.accepts(actual) {
+                        self.error(
+                            keyword.line,
+                            format!("Generator yields {} but is declared to yield {}.", actual, expected),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Reports patterns that can never match a value of the `subject` type.
+    fn pattern(&mut self, pattern: &Pattern, subject: Typed, line: usize) {
+        match pattern {
+            Pattern::Literal { value } => {
+                let actual = self.expression(value);
+                if actual.or(subject) && !actual.ty.accepts(subject.ty) {
+                    self.error(
+                        line,
+                        format!("Pattern of type {} can never match a value of type {}.", actual.ty, subject.ty),
+                    );
+                }
+            }
+            Pattern::Range { start, end, .. } => {
+                let low = self.expression(start);
+                let high = self.expression(end);
+                if !(low.or(high) || subject.declared) {
+                    return;
+                }
+                if !Type::Num.accepts(low.ty) || !Type::Num.accepts(high.ty) {
+                    self.error(
+                        line,
+                        format!("Range pattern bounds must be numbers, found {} and {}.", low.ty, high.ty),
+                    );
+                } else if !Type::Num.accepts(subject.ty) {
+                    self.error(line, format!("Range pattern can never match a value of type {}.", subject.ty));
+                }
+            }
+            Pattern::Binding { name } => self.define(&name.lexeme, Binding::Value(subject)),
+            Pattern::Wildcard { .. } => {}
+        }
+    }
+
+    fn declare_function(&mut self, stmt: &Stmt) {
+        if let Stmt::Function {
+            name,
+            param_types,
+            return_type,
+            body,
+            ..
+        } = stmt
+        {
+            let params = param_types.iter().map(|ty| self.annotation_type(ty).ty).collect();
+            // Calling a generator function gives a generator, whatever it yields.
+            let returns = if body.iter().any(Stmt::contains_yield) {
+                Typed::any()
+            } else {
+                self.annotation_type(return_type)
+            };
+            self.define(&name.lexeme, Binding::Function { params, returns });
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) -> Typed {
+        match expr {
+            Expr::Literal(literal) => Typed::inferred(match literal.value.downcast_ref::<TokenLiteral>() {
+                Some(TokenLiteral::Integer(_) | TokenLiteral::BigInteger(_) | TokenLiteral::Number(_)) => Type::Num,
+                Some(TokenLiteral::String(_)) => Type::Str,
+                Some(TokenLiteral::Boolean(_)) => Type::Bool,
+                Some(TokenLiteral::Null) => Type::Nil,
+                _ => Type::Any,
+            }),
+            Expr::Grouping(g) => self.expression(&g.expression),
+            Expr::Variable(v) => self.variable(&v.name.lexeme),
+            Expr::Assign { name, value } => {
+                let actual = self.expression(value);
+                if let Some(declared) = self.declared_variable(&name.lexeme) {
+                    if !declared.accepts(actual.ty) {
+                        self.error(
+                            name.line,
+                            format!("Cannot assign {} to '{}' of type {}.", actual.ty, name.lexeme, declared),
+                        );
+                    }
+                }
+                actual
+            }
+            Expr::CompoundAssign {
+                name,
+                operator,
+                value,
+            } => {
+                let right = self.expression(value);
+                let current = self.variable(&name.lexeme);
+                let base = Token {
+                    token_type: operator.token_type.arithmetic_base().unwrap_or(operator.token_type),
+                    ..operator.clone()
+                };
+                let actual = self.binary(&base, current, right);
+                if let Some(declared) = self.declared_variable(&name.lexeme) {
+                    if !declared.accepts(actual.ty) {
+                        self.error(
+                            name.line,
+                            format!("Cannot assign {} to '{}' of type {}.", actual.ty, name.lexeme, declared),
+                        );
+                    }
+                }
+                actual
+            }
+            Expr::Increment { name, operator, .. } => {
+                if let Some(declared) = self.declared_variable(&name.lexeme) {
+                    if !Type::Num.accepts(declared) {
+                        self.error(
+                            operator.line,
+                            format!("Operand of '{}' must be a number, found {}.", operator.lexeme, declared),
+                        );
+                    }
+                }
+                Typed::inferred(Type::Num)
+            }
+            Expr::Unary(u) => {
+                let right = self.expression(&u.right);
+                let ty = match u.operator.token_type {
+                    TokenType::MINUS => {
+                        if right.declared && !Type::Num.accepts(right.ty) {
+                            self.error(u.operator.line, format!("Operand of '-' must be a number, found {}.", right.ty));
+                        }
+                        Type::Num
+                    }
+                    TokenType::TILDE => {
+                        if right.declared && !Type::Num.accepts(right.ty) {
+                            self.error(u.operator.line, format!("Operand of '~' must be an integer, found {}.", right.ty));
+                        }
+                        Type::Num
+                    }
+                    _ => Type::Bool,
+                };
+                Typed { ty, declared: right.declared }
+            }
+            Expr::Binary(b) => {
+                let left = self.expression(&b.left);
+                let right = self.expression(&b.right);
+                self.binary(&b.operator, left, right)
+            }
+            Expr::Logical { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+                Typed::inferred(Type::Bool)
+            }
+            // Any value can be interpolated.
+            Expr::Interpolation { parts } => {
+                for part in parts {
+                    self.expression(part);
+                }
+                Typed::inferred(Type::Str)
+            }
+            Expr::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expression(condition);
+                let then_type = self.expression(then_branch);
+                let else_type = match else_branch {
+                    Some(else_branch) => self.expression(else_branch),
+                    None => Typed::inferred(Type::Nil),
+                };
+                if then_type.ty == else_type.ty {
+                    Typed { ty: then_type.ty, declared: then_type.or(else_type) }
+                } else {
+                    Typed::any()
+                }
+            }
+            Expr::Range {
+                start,
+                operator,
+                end,
+                step,
+            } => {
+                let left = self.expression(start);
+                let right = self.expression(end);
+                if left.or(right) && (!Type::Num.accepts(left.ty) || !Type::Num.accepts(right.ty)) {
+                    self.operand_error(operator, "integers", left.ty, right.ty);
+                }
+                if let Some(step) = step {
+                    let actual = self.expression(step);
+                    if actual.declared && !Type::Num.accepts(actual.ty) {
+                        self.error(operator.line, format!("Range step must be an integer, found {}.", actual.ty));
+                    }
+                }
+                Typed { ty: Type::Range, declared: left.or(right) }
+            }
+            Expr::Call {
+                callee,
+                arguments,
+                paren,
+            } => {
+                let callee_type = self.expression(callee);
+                let args: Vec<Type> = arguments
+                    .iter()
+                    .map(|arg| self.expression(arg).ty)
+                    .collect();
+
+                if !Type::Fun.accepts(callee_type.ty) {
+                    if callee_type.declared {
+                        self.error(paren.line, format!("Cannot call a value of type {}.", callee_type.ty));
+                    }
+                    return Typed::any();
+                }
+                let Expr::Variable(v) = callee.as_ref() else {
+                    return Typed::any();
+                };
+                let Some(Binding::Function { params, returns }) = self.lookup(&v.name.lexeme) else {
+                    return Typed::any();
+                };
+                for (index, (expected, actual)) in params.iter().zip(&args).enumerate() {
+                    if !expected.accepts(*actual) {
+                        self.error(
+                            paren.line,
+                            format!(
+                                "Argument {} of '{}' must be {}, found {}.",
+                                index + 1,
+                                v.name.lexeme,
+                                expected,
+                                actual
+                            ),
+                        );
+                    }
+                }
+                returns
+            }
+        }
+    }
+
+    // Mirrors the operand rules in `Interpreter::evaluate`. Operands that are only known from
+    // literals are never reported; at least one side must come from an annotation.
+    fn binary(&mut self, operator: &Token, left: Typed, right: Typed) -> Typed {
+        let declared = left.or(right);
+        let (l, r) = (left.ty, right.ty);
+        let known = l != Type::Any && r != Type::Any;
+        let ty = match operator.token_type {
+            TokenType::PLUS => {
+                if l == Type::Str || r == Type::Str {
+                    return Typed { ty: Type::Str, declared };
+                }
+                if declared && known && !(l == Type::Num && r == Type::Num) {
+                    self.operand_error(operator, "two numbers or two strings", l, r);
+                }
+                if known { Type::Num } else { Type::Any }
+            }
+            TokenType::MINUS
+            | TokenType::STAR
+            | TokenType::SLASH
+            | TokenType::PERCENT
+            | TokenType::TILDE_SLASH
+            | TokenType::STAR_STAR => {
+                if declared && (!Type::Num.accepts(l) || !Type::Num.accepts(r)) {
+                    self.operand_error(operator, "numbers", l, r);
+                }
+                Type::Num
+            }
+            TokenType::AMPERSAND
+            | TokenType::PIPE
+            | TokenType::CARET
+            | TokenType::LESS_LESS
+            | TokenType::GREATER_GREATER => {
+                if declared && (!Type::Num.accepts(l) || !Type::Num.accepts(r)) {
+                    self.operand_error(operator, "integers", l, r);
+                }
+                Type::Num
+            }
+            TokenType::GREATER | TokenType::GREATER_EQUAL | TokenType::LESS | TokenType::LESS_EQUAL => {
+                if declared && (!Type::Num.accepts(l) || !Type::Num.accepts(r)) {
+                    self.operand_error(operator, "numbers", l, r);
+                }
+                Type::Bool
+            }
+            TokenType::EQUAL_EQUAL | TokenType::BANG_EQUAL => {
+                let comparable = matches!((l, r), (Type::Num, Type::Num) | (Type::Str, Type::Str));
+                if declared && known && !comparable {
+                    self.operand_error(operator, "two numbers or two strings", l, r);
+                }
+                Type::Bool
+            }
+            _ => Type::Any,
+        };
+        Typed { ty, declared }
+    }
+
+    fn operand_error(&mut self, operator: &Token, expected: &str, left: Type, right: Type) {
+        self.error(
+            operator.line,
+            format!(
+                "Operands of '{}' must be {}, found {} and {}.",
+                operator.lexeme, expected, left, right
+            ),
+        );
+    }
+
+    fn variable(&self, name: &str) -> Typed {
+        match self.lookup(name) {
+            Some(Binding::Value(ty)) => ty,
+            Some(Binding::Function { .. }) => Typed::inferred(Type::Fun),
+            None => Typed::any(),
+        }
+    }
+
+    // The annotated type of a variable being assigned. A name without an annotation can be given
+    // any value, so assigning to it rebinds it to `Any` where it was defined.
+    fn declared_variable(&mut self, name: &str) -> Option<Type> {
+        let scope = self.scopes.iter_mut().rev().find(|scope| scope.contains_key(name))?;
+        match scope.get(name) {
+            Some(Binding::Value(typed)) if typed.declared => Some(typed.ty),
+            _ => {
+                scope.insert(name.to_string(), Binding::Value(Typed::any()));
+                None
+            }
+        }
+    }
+
+    // Resolves an annotation, reporting unknown type names. Missing annotations mean `Any`.
+    fn annotation(&mut self, token: &Option<Token>) -> Typed {
+        if let Some(token) = token {
+            if Type::from_annotation(token).is_none() {
+                self.error(token.line, format!("Unknown type '{}'.", token.lexeme));
+            }
+        }
+        self.annotation_type(token)
+    }
+
+    fn annotation_type(&self, token: &Option<Token>) -> Typed {
+        Typed {
+            ty: token
+                .as_ref()
+                .and_then(Type::from_annotation)
+                .unwrap_or(Type::Any),
+            declared: token.is_some(),
+        }
+    }
+
+    fn define(&mut self, name: &str, binding: Binding) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), binding);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).cloned())
+    }
+
+    fn error(&mut self, line: usize, message: String) {
+        self.errors.push(TypeError { line, message });
+    }
+}
@@ -0,0 +1,53 @@
+// Helpers for the tests that run the `aoi` binary the way a user would. Each test crate uses
+// only some of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+pub const PROGRAM: &str = "fun square(n) {
+    return n * n;
+}
+var total = 0;
+for (var i = 0; i < 3; i++) {
+    total += square(i);
+}
+if (total > 100) {
+    write(\"big\");
+}
+write(total);
+";
+
+// A fresh directory for one test, holding `files`.
+pub fn workspace(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("cli").join(test);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (name, source) in files {
+        fs::write(dir.join(name), source).unwrap();
+    }
+    dir
+}
+
+pub fn run(dir: &PathBuf, args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run aoi");
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
@@ -0,0 +1,101 @@
+// Golden-file conformance tests. Every `tests/conformance/*.aoi` program is run through the
+// CLI (which calls `run_code`) and its stdout and stderr are compared with the `.expected`
+// file next to it.
+//
+//     cargo test --test conformance                 # check
+//     cargo test --test conformance -- --bless      # rewrite the .expected files
+//     cargo test --test conformance -- return       # only cases whose name contains "return"
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let bless = args.iter().any(|arg| arg == "--bless") || env::var_os("AOI_BLESS").is_some();
+    let filters: Vec<&String> = args.iter().filter(|arg| !arg.starts_with('-')).collect();
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("conformance");
+    let mut cases: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("tests/conformance is missing")
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "aoi"))
+        .filter(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy();
+            filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str()))
+        })
+        .collect();
+    cases.sort();
+
+    let mut failed = Vec::new();
+    for case in &cases {
+        let name = case.file_stem().unwrap().to_string_lossy().to_string();
+        let expected_path = case.with_extension("expected");
+        let actual = run(case);
+
+        if bless {
+            fs::write(&expected_path, &actual).expect("failed to write .expected file");
+            println!("blessed {}", name);
+            continue;
+        }
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => println!("ok      {}", name),
+            Ok(expected) => {
+                println!("FAILED  {}", name);
+                print!("{}", diff(&expected, &actual));
+                failed.push(name);
+            }
+            Err(_) => {
+                println!("FAILED  {} (no {}; run with --bless)", name, expected_path.display());
+                failed.push(name);
+            }
+        }
+    }
+
+    println!();
+    println!("{} cases: {} passed, {} failed", cases.len(), cases.len() - failed.len(), failed.len());
+    if !failed.is_empty() {
+        exit(1);
+    }
+}
+
+// What the program printed, with stderr and a non-zero exit status appended when present.
+fn run(case: &Path) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rust"))
+        .arg(case)
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .expect("failed to run the interpreter");
+    let mut result = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.is_empty() {
+        result.push_str("--- stderr ---\n");
+        result.push_str(&stderr);
+    }
+    if !output.status.success() {
+        result.push_str(&format!("--- exit status: {} ---\n", output.status.code().unwrap_or(-1)));
+    }
+    result
+}
+
+// Line-by-line comparison, `-` for expected and `+` for actual.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut out = String::new();
+    for index in 0..expected.len().max(actual.len()) {
+        match (expected.get(index), actual.get(index)) {
+            (Some(e), Some(a)) if e == a => out.push_str(&format!("          {}\n", e)),
+            (e, a) => {
+                if let Some(e) = e {
+                    out.push_str(&format!("        - {}\n", e));
+                }
+                if let Some(a) = a {
+                    out.push_str(&format!("        + {}\n", a));
+                }
+            }
+        }
+    }
+    out
+}
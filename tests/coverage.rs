@@ -0,0 +1,25 @@
+// `coverage` and `--lcov`: the annotated listing, the summary line and the lcov records.
+mod common;
+
+use common::{run, stderr, stdout, workspace, PROGRAM};
+use std::fs;
+
+#[test]
+fn coverage_marks_missed_lines_and_writes_lcov() {
+    let dir = workspace("coverage", &[("prog.aoi", PROGRAM)]);
+    let output = run(&dir, &["coverage", "--lcov", "out.lcov", "prog.aoi"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let report = stdout(&output) + &stderr(&output);
+    assert!(report.contains("Covered 7 of 8 lines (87.5%)"), "{}", report);
+    let missed = report.lines().find(|line| line.contains("write(\"big\")")).unwrap();
+    assert!(missed.contains("#####"), "{}", missed);
+
+    let lcov = fs::read_to_string(dir.join("out.lcov")).unwrap();
+    let records: Vec<&str> = lcov.lines().collect();
+    assert!(records.contains(&"SF:prog.aoi"), "{}", lcov);
+    assert!(records.contains(&"DA:2,3"), "{}", lcov);
+    assert!(records.contains(&"DA:9,0"), "{}", lcov);
+    assert!(records.contains(&"LF:8"), "{}", lcov);
+    assert!(records.contains(&"LH:7"), "{}", lcov);
+    assert_eq!(records.last(), Some(&"end_of_record"));
+}
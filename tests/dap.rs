@@ -0,0 +1,79 @@
+// `dap`: a scripted client drives the debug adapter over stdin and stdout.
+mod common;
+
+use aoi::lsp::read_message;
+use common::{run, stderr, workspace, PROGRAM};
+use serde_json::{json, Value};
+use std::io::Cursor;
+
+fn dap_request(seq: u64, command: &str, arguments: Value) -> Vec<u8> {
+    let body = json!({ "seq": seq, "type": "request", "command": command, "arguments": arguments }).to_string();
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body).into_bytes()
+}
+
+#[test]
+fn debug_adapter_stops_at_breakpoints_and_steps() {
+    let dir = workspace("dap", &[("prog.aoi", PROGRAM)]);
+    // The adapter reads requests as it needs them, so the whole session can be scripted.
+    let script: Vec<u8> = [
+        dap_request(1, "initialize", json!({})),
+        dap_request(2, "launch", json!({ "program": "prog.aoi" })),
+        dap_request(3, "setBreakpoints", json!({ "source": { "path": "prog.aoi" }, "breakpoints": [{ "line": 6 }] })),
+        dap_request(4, "configurationDone", json!({})),
+        dap_request(5, "evaluate", json!({ "expression": "i" })),
+        dap_request(6, "stepIn", json!({ "threadId": 1 })),
+        dap_request(7, "stackTrace", json!({ "threadId": 1 })),
+        dap_request(8, "stepOut", json!({ "threadId": 1 })),
+        dap_request(9, "evaluate", json!({ "expression": "i" })),
+        dap_request(10, "disconnect", json!({})),
+    ]
+    .concat();
+    let output = run(&dir, &["dap"], &script);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let mut reader = Cursor::new(output.stdout);
+    let mut messages = Vec::new();
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        messages.push(message);
+    }
+    let response = |seq: u64| -> &Value {
+        messages
+            .iter()
+            .find(|m| m["type"] == "response" && m["request_seq"] == seq)
+            .unwrap_or_else(|| panic!("no response to request {}", seq))
+    };
+    let stops: Vec<&Value> = messages.iter().filter(|m| m["event"] == "stopped").map(|m| &m["body"]["reason"]).collect();
+    assert_eq!(stops, ["breakpoint", "step", "breakpoint"]);
+
+    assert_eq!(response(3)["body"]["breakpoints"][0]["verified"], true);
+    assert_eq!(response(5)["body"]["result"], "0");
+    let frames = response(7)["body"]["stackFrames"].as_array().unwrap();
+    let frames: Vec<(&str, &Value)> = frames.iter().map(|f| (f["name"].as_str().unwrap(), &f["line"])).collect();
+    assert_eq!(frames, [("square", &json!(2)), ("<script>", &json!(6))]);
+    // Stepping out of `square` runs on to the breakpoint on the next iteration.
+    assert_eq!(response(9)["body"]["result"], "1");
+    assert!(response(10)["success"].as_bool().unwrap());
+}
+
+#[test]
+fn debug_adapter_reports_syntax_errors_with_their_line() {
+    let dir = workspace("dap_syntax_error", &[("bad.aoi", "var a = 1;\n1 = 2;\n")]);
+    let script: Vec<u8> = [
+        dap_request(1, "initialize", json!({})),
+        dap_request(2, "launch", json!({ "program": "bad.aoi" })),
+        dap_request(3, "configurationDone", json!({})),
+        dap_request(4, "disconnect", json!({})),
+    ]
+    .concat();
+    let output = run(&dir, &["dap"], &script);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+
+    let mut reader = Cursor::new(output.stdout);
+    let mut text = String::new();
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        if message["event"] == "output" {
+            text.push_str(message["body"]["output"].as_str().unwrap());
+        }
+    }
+    assert!(text.contains("[line 2] Error at '=': Invalid assignment target.\n"), "{}", text);
+}
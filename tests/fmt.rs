@@ -0,0 +1,179 @@
+// `aoi fmt`: how `format_source` lays code out, and what the command does with files it
+// can't format.
+use aoi::cst;
+use aoi::formatter::format_source;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+
+fn fmt_file(name: &str, source: &str) -> (PathBuf, Option<i32>) {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, source).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_rust"))
+        .arg("fmt")
+        .arg(&path)
+        .output()
+        .expect("failed to run the formatter")
+        .status;
+    (path, status.code())
+}
+
+#[test]
+fn refuses_input_the_tokenizer_rejected() {
+    for source in [
+        "var a = 1 @;\n",
+        "var n = 0x;\n",
+        "var s = \"open;\n",
+        "write(\"\\q\");\n",
+        "var a = 1; /* open\n",
+    ] {
+        assert!(format_source(source).is_err(), "formatted {:?}", source);
+    }
+}
+
+#[test]
+fn leaves_files_with_tokenizer_errors_alone() {
+    let source = "var a = 1 @;\n";
+    let (path, status) = fmt_file("tokenizer_error.aoi", source);
+    assert_eq!(status, Some(2));
+    assert_eq!(fs::read_to_string(path).unwrap(), source);
+}
+
+#[test]
+fn leaves_files_with_syntax_errors_alone() {
+    for (name, source) in [("missing_initializer.aoi", "var x = ;\n"), ("missing_operand.aoi", "write(1 + );\n")] {
+        assert!(format_source(source).is_err(), "formatted {:?}", source);
+        let (path, status) = fmt_file(name, source);
+        assert_eq!(status, Some(2));
+        assert_eq!(fs::read_to_string(path).unwrap(), source);
+    }
+}
+
+#[test]
+fn rewrites_files_in_place() {
+    let (path, status) = fmt_file("rewrite.aoi", "var   a=1;write( a );\n");
+    assert_eq!(status, Some(0));
+    assert_eq!(fs::read_to_string(path).unwrap(), "var a = 1;\nwrite(a);\n");
+}
+
+// Each case is formatted and compared with the expected layout, which must itself be stable.
+fn assert_formats(source: &str, expected: &str) {
+    let formatted = format_source(source).unwrap();
+    assert_eq!(formatted, expected, "formatting {:?}", source);
+    assert_eq!(format_source(&formatted).unwrap(), formatted, "formatting {:?} twice", source);
+}
+
+#[test]
+fn block_comments_stay_in_front_of_the_code_after_them() {
+    assert_formats("/* lead */ var a = 1;\n", "/* lead */ var a = 1;\n");
+    assert_formats("fun f(/* p */ a) {}\n", "fun f(/* p */ a) {}\n");
+    assert_formats("var b = /* one */ 1 /* op */ + 2;\n", "var b = /* one */ 1 /* op */ + 2;\n");
+    assert_formats("f(/* a */ x,  /* b */ y);\n", "f(/* a */ x, /* b */ y);\n");
+    assert_formats(
+        "match (n) {\n  /* one */ 1 => write(1);\n  _ => write(2);\n}\n",
+        "match (n) {\n    /* one */ 1 => write(1);\n    _ => write(2);\n}\n",
+    );
+}
+
+#[test]
+fn block_comments_stay_inside_their_block() {
+    assert_formats("fun f(/* p */ a) { /* in */ }\n", "fun f(/* p */ a) { /* in */\n}\n");
+    assert_formats(
+        "if (true) { /* x */ } else {}\n",
+        "if (true) { /* x */\n} else {}\n",
+    );
+    assert_formats(
+        "while (x) {\n  x = false;\n  /* last */ }\n",
+        "while (x) {\n    x = false;\n    /* last */\n}\n",
+    );
+}
+
+#[test]
+fn trailing_comments_stay_on_their_line() {
+    assert_formats("var a = 1; // one\nvar b = 2; /* two */\n", "var a = 1; // one\nvar b = 2; /* two */\n");
+}
+
+#[test]
+fn comments_between_statements_are_kept_where_they_are() {
+    let source = "if (true) { // open
+    // first
+    write(1); // one
+    // last
+} else {
+    write(2);
+}
+match (3) {
+    // small
+    1 => write(1);
+    _ => {}
+}
+";
+    assert_formats(source, source);
+}
+
+// These would all be written after the statement, or inside the block that follows.
+#[test]
+fn leaves_files_with_comments_inside_expressions_alone() {
+    for (name, source) in [
+        ("comment_between_arguments.aoi", "write(max(1, // first\n    2));\n"),
+        ("comment_before_argument.aoi", "write(max(\n    // larger\n    1, 2));\n"),
+        ("comment_in_condition.aoi", "if (true // always\n) {\n}\n"),
+        ("comment_before_else.aoi", "if (true) {\n} // then\nelse {\n}\n"),
+        ("comment_in_for_header.aoi", "for (var i = 0; // start\n    i < 2; i++) {\n}\n"),
+    ] {
+        assert!(format_source(source).is_err(), "formatted {:?}", source);
+        let (path, status) = fmt_file(name, source);
+        assert_eq!(status, Some(2));
+        assert_eq!(fs::read_to_string(path).unwrap(), source);
+    }
+}
+
+fn conformance_sources() -> Vec<(PathBuf, String)> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("conformance");
+    let mut sources: Vec<(PathBuf, String)> = fs::read_dir(root)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "aoi"))
+        .map(|path| {
+            let source = fs::read_to_string(&path).unwrap();
+            (path, source)
+        })
+        .collect();
+    sources.sort();
+    sources
+}
+
+// `nesting_limit.aoi` goes as deep as the parser allows, so check on a stack the size of
+// the main thread's.
+fn on_main_sized_stack(check: fn()) {
+    thread::Builder::new().stack_size(8 * 1024 * 1024).spawn(check).unwrap().join().unwrap();
+}
+
+#[test]
+fn formatting_conformance_cases_is_idempotent() {
+    on_main_sized_stack(|| {
+        let mut formatted_any = false;
+        for (path, source) in conformance_sources() {
+            // Cases that exercise syntax errors are refused, which is covered above.
+            let Ok(formatted) = format_source(&source) else { continue };
+            formatted_any = true;
+            assert_eq!(
+                format_source(&formatted).unwrap(),
+                formatted,
+                "{} changes when formatted twice",
+                path.display()
+            );
+        }
+        assert!(formatted_any);
+    });
+}
+
+#[test]
+fn lossless_tree_reproduces_conformance_cases() {
+    on_main_sized_stack(|| {
+        for (path, source) in conformance_sources() {
+            assert_eq!(cst::parse(&source).text(), source, "{}", path.display());
+        }
+    });
+}
@@ -0,0 +1,95 @@
+// Replays the fuzz seeds (`tests/conformance/*.aoi`) and every input kept in
+// `fuzz/regressions/` through the same stages as the fuzz targets, so inputs that once
+// crashed stay fixed without needing nightly or cargo-fuzz.
+use aoi::cst;
+use aoi::interpreter::Interpreter;
+use aoi::parser::Parser;
+use aoi::token::Tokensizer;
+use aoi::typechecker;
+use std::fs;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+// Matches `fuzz/fuzz_targets/eval.rs`.
+const STEP_LIMIT: usize = 10_000;
+const OUTPUT_LIMIT: usize = 1 << 24;
+
+fn inputs() -> Vec<PathBuf> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut inputs = Vec::new();
+    for dir in [root.join("tests").join("conformance"), root.join("fuzz").join("regressions")] {
+        let entries = fs::read_dir(&dir).unwrap_or_else(|_| panic!("{} is missing", dir.display()));
+        inputs.extend(
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "aoi")),
+        );
+    }
+    inputs.sort();
+    inputs
+}
+
+// tokenize, parse + type-check, then run with step and output limits, as the three targets
+// do. The lossless parser behind `fmt` and the language server gets the same input and must
+// give it back unchanged.
+fn replay(source: &str) {
+    assert_eq!(cst::parse(source).text(), source);
+    let tokens = Tokensizer::new(source.to_string()).tokenize();
+    let Some(statements) = Parser::new(tokens).parse() else {
+        return;
+    };
+    if !typechecker::check(&statements).is_empty() || source.contains("scan") {
+        return;
+    }
+    let mut interpreter = Interpreter::new();
+    interpreter.set_step_limit(STEP_LIMIT);
+    interpreter.set_output_limit(OUTPUT_LIMIT);
+    interpreter.interpret(&statements);
+}
+
+#[test]
+fn no_input_panics() {
+    let mut failed = Vec::new();
+    for path in inputs() {
+        let source = String::from_utf8_lossy(&fs::read(&path).unwrap()).to_string();
+        // The CLI runs on the main thread, so give each input a stack of the same size.
+        let result = thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(move || panic::catch_unwind(|| replay(&source)))
+            .unwrap()
+            .join()
+            .unwrap();
+        if result.is_err() {
+            failed.push(path.display().to_string());
+        }
+    }
+    assert!(failed.is_empty(), "inputs that panicked:\n{}", failed.join("\n"));
+}
+
+// `fmt` and the language server use the lossless parser, everything else `Parser`. Both must
+// reject the same inputs, or one of them would accept code the other turns into something else.
+#[test]
+fn both_parsers_accept_the_same_inputs() {
+    let mut disagree = Vec::new();
+    for path in inputs() {
+        let source = String::from_utf8_lossy(&fs::read(&path).unwrap()).to_string();
+        let verdicts = thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(move || {
+                let mut tokenizer = Tokensizer::new(source.clone());
+                let tokens = tokenizer.tokenize();
+                let parsed = Parser::new(tokens).parse().is_some() && !tokenizer.had_error();
+                let lossless = cst::parse_with_errors(&source).1.is_empty();
+                (parsed, lossless)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        if verdicts.0 != verdicts.1 {
+            disagree.push(format!("{} (Parser: {}, lossless: {})", path.display(), verdicts.0, verdicts.1));
+        }
+    }
+    assert!(disagree.is_empty(), "parsers disagree on:\n{}", disagree.join("\n"));
+}
@@ -0,0 +1,33 @@
+// `tokens --json` and `ast --json`: the token stream and syntax tree with byte spans, for
+// editors and other tools.
+mod common;
+
+use common::{run, stderr, stdout, workspace};
+use serde_json::{json, Value};
+
+#[test]
+fn tokens_and_ast_as_json_carry_byte_spans() {
+    let dir = workspace("json", &[("prog.aoi", "var a = 1;\nwrite(a);\n")]);
+
+    let output = run(&dir, &["tokens", "--json", "prog.aoi"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let tokens: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(tokens[0]["lexeme"], "var");
+    assert_eq!(tokens[0]["line"], 1);
+    assert_eq!(tokens[0]["span"], json!({ "start": 0, "end": 3 }));
+    assert_eq!(tokens[3]["literal"], json!({ "Integer": 1 }));
+    assert_eq!(tokens[5]["lexeme"], "write");
+    assert_eq!(tokens[5]["span"], json!({ "start": 11, "end": 16 }));
+
+    let output = run(&dir, &["ast", "--json", "prog.aoi"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let ast: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let statements = ast.as_array().unwrap();
+    assert_eq!(statements.len(), 2);
+    assert_eq!(statements[0]["type"], "Var");
+    assert_eq!(statements[0]["span"], json!({ "start": 0, "end": 10 }));
+    assert_eq!(statements[0]["initializer"]["span"], json!({ "start": 8, "end": 9 }));
+    assert_eq!(statements[1]["type"], "Print");
+    assert_eq!(statements[1]["line"], 2);
+    assert_eq!(statements[1]["span"], json!({ "start": 11, "end": 20 }));
+}
@@ -0,0 +1,41 @@
+// `lint`: which rules fire on which lines, and how `// lint:` comments and `--allow` turn
+// them off.
+mod common;
+
+use common::{run, stderr, stdout, workspace};
+use std::fs;
+
+#[test]
+fn lint_reports_rules_and_honours_suppressions() {
+    let source = "var x = 1;
+x = x;
+var y = 2; // lint: allow(unused-variable)
+if (true) {}
+fun g(a) {}
+fun h() {
+    // Nothing to do yet.
+}
+g(1);
+h();
+";
+    let dir = workspace("lint", &[("l.aoi", source)]);
+
+    let output = run(&dir, &["lint", "l.aoi"], b"");
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output) + &stderr(&output);
+    assert!(report.contains("[line 2] warning[self-assignment]: 'x' is assigned to itself."), "{}", report);
+    assert!(report.contains("[line 4] warning[constant-condition]"), "{}", report);
+    assert!(report.contains("[line 4] warning[empty-block]"), "{}", report);
+    assert!(report.contains("[line 5] warning[empty-block]"), "{}", report);
+    assert!(!report.contains("[line 6]"), "{}", report);
+    assert!(!report.contains("[line 3]"), "{}", report);
+
+    let output = run(&dir, &["lint", "--allow", "empty-block", "l.aoi"], b"");
+    let report = stdout(&output) + &stderr(&output);
+    assert!(!report.contains("empty-block"), "{}", report);
+    assert!(report.contains("self-assignment"), "{}", report);
+
+    fs::write(dir.join("clean.aoi"), "// lint: allow-file(empty-block)\nwhile (false) {}\n").unwrap();
+    let output = run(&dir, &["lint", "--allow", "constant-condition", "clean.aoi"], b"");
+    assert_eq!(output.status.code(), Some(0), "{}", stdout(&output) + &stderr(&output));
+}
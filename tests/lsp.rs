@@ -0,0 +1,188 @@
+// Drives `LanguageServer::handle` the way an editor would: initialize, open a document,
+// then ask for diagnostics, definitions, hovers, symbols and completions.
+use aoi::lsp::LanguageServer;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::thread;
+
+const URI: &str = "file:///test.aoi";
+
+const SOURCE: &str = "/// Adds two numbers.
+fun add(a: num, b: num): num {
+    var sum = a + b;
+    return sum;
+}
+var total: num = add(1, 2);
+var wrong: num = \"three\";
+";
+
+fn request(server: &mut LanguageServer, id: u64, method: &str, params: Value) -> Value {
+    let replies = server.handle(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+    assert_eq!(replies.len(), 1, "{} should get exactly one reply: {:?}", method, replies);
+    assert_eq!(replies[0]["id"], id);
+    replies[0]["result"].clone()
+}
+
+fn open(server: &mut LanguageServer, text: &str) -> Vec<Value> {
+    let replies = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": URI, "languageId": "aoi", "version": 1, "text": text } }
+    }));
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(replies[0]["params"]["uri"], URI);
+    replies[0]["params"]["diagnostics"].as_array().unwrap().clone()
+}
+
+fn at(line: u64, character: u64) -> Value {
+    json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+}
+
+// An initialized server with `SOURCE` open, and the diagnostics it published.
+fn server() -> (LanguageServer, Vec<Value>) {
+    let mut server = LanguageServer::new();
+    let result = request(&mut server, 1, "initialize", json!({ "capabilities": {} }));
+    assert_eq!(result["serverInfo"]["name"], "aoi");
+    assert_eq!(result["capabilities"]["hoverProvider"], true);
+    server.handle(&json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }));
+    let diagnostics = open(&mut server, SOURCE);
+    (server, diagnostics)
+}
+
+#[test]
+fn publishes_type_errors_and_lint_warnings_on_their_line() {
+    let (_, diagnostics) = server();
+    let summary: Vec<(u64, u64, &str)> = diagnostics
+        .iter()
+        .map(|d| {
+            (
+                d["severity"].as_u64().unwrap(),
+                d["range"]["start"]["line"].as_u64().unwrap(),
+                d["message"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (1, 6, "Variable 'wrong' is declared num but initialized with str."),
+            (2, 5, "Variable 'total' is declared but never read. [unused-variable]"),
+            (2, 6, "Variable 'wrong' is declared but never read. [unused-variable]"),
+        ]
+    );
+}
+
+#[test]
+fn publishes_the_parser_error_at_its_token() {
+    let mut server = LanguageServer::new();
+    let diagnostics = open(&mut server, "var a = 1;\nwrite(a);\n1 = 2;\n");
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0]["message"], "Invalid assignment target.");
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({ "start": { "line": 2, "character": 2 }, "end": { "line": 2, "character": 3 } })
+    );
+
+    let diagnostics = open(&mut server, "var x = ;\n");
+    assert_eq!(diagnostics[0]["message"], "Expect expression.");
+    assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 0, "character": 8 }));
+}
+
+#[test]
+fn goes_to_the_declaration() {
+    let (mut server, _) = server();
+    // `add` in `add(1, 2)`
+    let result = request(&mut server, 2, "textDocument/definition", at(5, 18));
+    assert_eq!(result["uri"], URI);
+    assert_eq!(result["range"]["start"], json!({ "line": 1, "character": 4 }));
+    // `sum` in `return sum;`
+    let result = request(&mut server, 3, "textDocument/definition", at(3, 12));
+    assert_eq!(result["range"]["start"], json!({ "line": 2, "character": 8 }));
+    // Nothing under a keyword.
+    assert_eq!(request(&mut server, 4, "textDocument/definition", at(3, 6)), Value::Null);
+}
+
+#[test]
+fn hover_shows_the_signature_and_doc_comment() {
+    let (mut server, _) = server();
+    let result = request(&mut server, 2, "textDocument/hover", at(5, 18));
+    assert_eq!(
+        result["contents"]["value"],
+        "```aoi\nfun add(a: num, b: num): num\n```\n\nAdds two numbers."
+    );
+    let result = request(&mut server, 3, "textDocument/hover", at(2, 14));
+    assert_eq!(result["contents"]["value"], "```aoi\n(parameter) a: num\n```");
+}
+
+#[test]
+fn lists_symbols_with_nested_locals() {
+    let (mut server, _) = server();
+    let result = request(&mut server, 2, "textDocument/documentSymbol", at(0, 0));
+    let names: Vec<&str> = result.as_array().unwrap().iter().map(|s| s["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["add", "total", "wrong"]);
+    let add = &result[0];
+    assert_eq!(add["kind"], 12);
+    assert_eq!(add["range"]["start"]["line"], 1);
+    assert_eq!(add["range"]["end"]["line"], 4);
+    // Parameters are not symbols, locals are.
+    assert_eq!(add["children"].as_array().unwrap().len(), 1);
+    assert_eq!(add["children"][0]["name"], "sum");
+}
+
+#[test]
+fn completes_names_in_scope_builtins_and_keywords() {
+    let (mut server, _) = server();
+    let labels = |result: Value| -> Vec<String> {
+        result
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let inside = labels(request(&mut server, 2, "textDocument/completion", at(3, 4)));
+    for name in ["sum", "a", "b", "add", "next", "while"] {
+        assert!(inside.contains(&name.to_string()), "{} missing from {:?}", name, inside);
+    }
+    let outside = labels(request(&mut server, 3, "textDocument/completion", at(6, 0)));
+    assert!(outside.contains(&"total".to_string()));
+    assert!(!outside.contains(&"sum".to_string()));
+}
+
+#[test]
+fn rejects_requests_for_unknown_documents() {
+    let (mut server, _) = server();
+    let replies = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "textDocument/hover",
+        "params": { "textDocument": { "uri": "file:///other.aoi" }, "position": { "line": 0, "character": 0 } }
+    }));
+    assert_eq!(replies[0]["error"]["code"], -32602);
+}
+
+#[test]
+fn survives_deeply_nested_documents() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz").join("regressions");
+    for name in ["deep_parentheses.aoi", "deep_blocks.aoi", "deep_unary.aoi", "long_operator_chain.aoi"] {
+        let text = fs::read_to_string(root.join(name)).unwrap();
+        // The server runs on the main thread, so give it a stack of the same size.
+        let diagnostics = thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(move || {
+                let mut server = LanguageServer::new();
+                open(&mut server, &text)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(
+            diagnostics.iter().any(|d| d["message"] == "Too deeply nested."),
+            "{}: {:?}",
+            name,
+            diagnostics
+        );
+    }
+}
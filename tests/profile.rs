@@ -0,0 +1,29 @@
+// `--profile` and `--folded`: the call and line tables on stderr, and folded stacks for
+// flame graphs. Timings vary, so only the shape is checked.
+mod common;
+
+use common::{run, stderr, stdout, workspace, PROGRAM};
+use std::fs;
+
+#[test]
+fn profile_reports_calls_and_writes_folded_stacks() {
+    let dir = workspace("profile", &[("prog.aoi", PROGRAM)]);
+    let output = run(&dir, &["--profile", "--folded", "out.folded", "prog.aoi"], b"");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).starts_with("5\n"), "{}", stdout(&output));
+
+    let report = stderr(&output);
+    assert!(report.contains("Function"), "{}", report);
+    let square = report.lines().find(|line| line.trim_start().starts_with("square")).unwrap();
+    assert_eq!(square.split_whitespace().nth(1), Some("3"), "{}", report);
+    assert!(report.contains("Hot lines"), "{}", report);
+
+    // Folded stacks are `frame;frame weight`, one stack per line.
+    let folded = fs::read_to_string(dir.join("out.folded")).unwrap();
+    let stacks: Vec<&str> = folded.lines().map(|line| line.rsplit_once(' ').unwrap().0).collect();
+    assert!(stacks.contains(&"<script>"), "{}", folded);
+    assert!(stacks.contains(&"<script>;square"), "{}", folded);
+    for line in folded.lines() {
+        line.rsplit_once(' ').unwrap().1.parse::<u64>().unwrap();
+    }
+}
@@ -0,0 +1,36 @@
+// `aoi test`: discovery of `*_test.aoi` files and `test_*` functions, the report, and the
+// exit status.
+mod common;
+
+use common::{run, stdout, workspace};
+use std::fs;
+
+#[test]
+fn test_runner_reports_each_test_and_fails_on_a_failure() {
+    let source = "fun add(a, b) { return a + b; }
+fun test_add() { assert_eq(add(2, 2), 4); }
+fun test_broken() { assert_eq(add(2, 2), 5, \"bad sum\"); }
+fun helper() { write(\"not a test\"); }
+fun test_generator() { assert(false); yield 1; }
+";
+    let dir = workspace("test", &[("math_test.aoi", source), ("other.aoi", "fun test_skipped() {}\n")]);
+    let output = run(&dir, &["test", "."], b"");
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output);
+    assert!(report.contains("  ok      test_add\n"), "{}", report);
+    assert!(report.contains("  FAILED  test_broken\n    assert_eq failed: bad sum\n      left:  4\n      right: 5\n"), "{}", report);
+    assert!(!report.contains("not a test"), "{}", report);
+    assert!(!report.contains("test_skipped"), "{}", report);
+    // Calling a generator only creates it, so it is reported rather than passed unrun.
+    assert!(
+        report.contains("  FAILED  test_generator\n    'test_generator' is a generator and would never run"),
+        "{}",
+        report
+    );
+    assert!(report.ends_with("3 tests: 1 passed, 2 failed\n"), "{}", report);
+
+    fs::remove_file(dir.join("math_test.aoi")).unwrap();
+    fs::write(dir.join("ok_test.aoi"), "fun test_one() { assert(true); }\n").unwrap();
+    let output = run(&dir, &["test", "."], b"");
+    assert_eq!(output.status.code(), Some(0), "{}", stdout(&output));
+}
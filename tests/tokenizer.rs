@@ -0,0 +1,24 @@
+// Tokenizing stays linear in the size of the input, even when every character is an error.
+use aoi::token::Tokensizer;
+use std::time::{Duration, Instant};
+
+// Generous for a debug build; reporting each error with its whole line took minutes here.
+const BUDGET: Duration = Duration::from_secs(5);
+
+fn tokenize_in_time(source: String) -> Tokensizer {
+    let started = Instant::now();
+    let mut tokenizer = Tokensizer::new(source);
+    tokenizer.tokenize();
+    assert!(started.elapsed() < BUDGET, "took {:?}", started.elapsed());
+    tokenizer
+}
+
+#[test]
+fn a_long_line_of_unexpected_characters_is_quick() {
+    assert!(tokenize_in_time("@".repeat(400_000)).had_error());
+}
+
+#[test]
+fn many_separate_unexpected_characters_on_one_line_are_quick() {
+    assert!(tokenize_in_time("@a".repeat(100_000)).had_error());
+}